@@ -0,0 +1,266 @@
+//! Generates the TM4C123GH6PM register map from the vendored SVD file
+//!
+//! The output (`$OUT_DIR/tm4c123gh6pm.rs`) is included by `src/lib/registers/mod.rs`. It has:
+//! - a `peripherals` module with the base address of every peripheral
+//! - an `interrupts` module with the interrupt number of every peripheral interrupt
+//! - one module per register block (`gpio_porta`, `uart0`, `sysctl`, ...) with a `RegisterSpec`
+//!   struct for every register and a `Field` constant for every field
+//! - a `vector_table` module with the interrupt part of the vector table (`__INTERRUPTS`),
+//!   pointing each interrupt at the handler of the same name
+//!
+//! It also writes `$OUT_DIR/device.x`, which cortex-m-rt's linker script includes to make every
+//! handler default to `DefaultHandler` unless something (like the GPIO driver) defines it
+//!
+//! Peripherals that are `derivedFrom` another (such as `GPIO_PORTB` from `GPIO_PORTA`)
+//! only get a base address and share the register block of the one they derive from
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+use roxmltree::{Document, Node};
+
+const SVD: &str = "svd/TM4C123GH6PM.svd";
+
+fn main() {
+    println!("cargo:rerun-if-changed={SVD}");
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let svd = fs::read_to_string(SVD).expect("the SVD file should be readable");
+    let document = Document::parse(&svd).expect("the SVD file should be valid XML");
+    let device = document.root_element();
+
+    let peripherals: Vec<Node> = device
+        .children()
+        .find(|node| node.has_tag_name("peripherals"))
+        .expect("the SVD file should have peripherals")
+        .children()
+        .filter(|node| node.has_tag_name("peripheral"))
+        .collect();
+
+    let mut out = String::new();
+    writeln!(out, "// Generated by build.rs from {SVD}. Do not edit.").unwrap();
+
+    generate_peripherals(&mut out, &peripherals);
+    generate_interrupts(&mut out, &peripherals);
+    generate_vector_table(&mut out, &peripherals);
+    for peripheral in &peripherals {
+        if peripheral.attribute("derivedFrom").is_none() {
+            generate_register_block(&mut out, peripheral);
+        }
+    }
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join("tm4c123gh6pm.rs"), out).unwrap();
+
+    fs::write(out_dir.join("device.x"), device_x(&peripherals)).unwrap();
+    println!("cargo:rustc-link-search={}", out_dir.display());
+}
+
+fn generate_peripherals(out: &mut String, peripherals: &[Node]) {
+    writeln!(out, "pub mod peripherals {{").unwrap();
+    for peripheral in peripherals {
+        let name = text(peripheral, "name");
+        let base = number(&text(peripheral, "baseAddress"));
+
+        write_doc(out, peripheral);
+        writeln!(out, "pub const {}: u32 = {};", identifier(&name), hex(base)).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+}
+
+fn generate_interrupts(out: &mut String, peripherals: &[Node]) {
+    writeln!(out, "pub mod interrupts {{").unwrap();
+    for interrupt in peripherals
+        .iter()
+        .flat_map(|peripheral| peripheral.children())
+        .filter(|node| node.has_tag_name("interrupt"))
+    {
+        let name = text(&interrupt, "name");
+        let value = number(&text(&interrupt, "value"));
+
+        write_doc(out, &interrupt);
+        writeln!(out, "pub const {}: u32 = {value};", identifier(&name)).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+}
+
+/// Every interrupt (each only once, even if more than one peripheral lists it), ordered by number
+fn interrupts(peripherals: &[Node]) -> Vec<(String, u32)> {
+    let mut interrupts: Vec<(String, u32)> = peripherals
+        .iter()
+        .flat_map(|peripheral| peripheral.children())
+        .filter(|node| node.has_tag_name("interrupt"))
+        .map(|interrupt| (text(&interrupt, "name"), number(&text(&interrupt, "value"))))
+        .collect();
+
+    interrupts.sort_by_key(|(_, value)| *value);
+    interrupts.dedup();
+    interrupts
+}
+
+fn generate_vector_table(out: &mut String, peripherals: &[Node]) {
+    let interrupts = interrupts(peripherals);
+    let length = interrupts.last().map_or(0, |(_, value)| value + 1);
+
+    // The host tests don't link against cortex-m-rt's linker script, so they don't need (or want) it
+    writeln!(out, "#[cfg(not(test))]").unwrap();
+    writeln!(out, "pub mod vector_table {{").unwrap();
+    writeln!(out, "pub union Vector {{").unwrap();
+    writeln!(out, "handler: unsafe extern \"C\" fn(),").unwrap();
+    writeln!(out, "reserved: usize,").unwrap();
+    writeln!(out, "}}").unwrap();
+
+    writeln!(out, "extern \"C\" {{").unwrap();
+    for (name, _) in &interrupts {
+        writeln!(out, "fn {name}();").unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+
+    writeln!(out, "#[link_section = \".vector_table.interrupts\"]").unwrap();
+    writeln!(out, "#[no_mangle]").unwrap();
+    writeln!(out, "pub static __INTERRUPTS: [Vector; {length}] = [").unwrap();
+    for number in 0..length {
+        match interrupts.iter().find(|(_, value)| *value == number) {
+            Some((name, _)) => writeln!(out, "Vector {{ handler: {name} }},").unwrap(),
+            None => writeln!(out, "Vector {{ reserved: 0 }},").unwrap(),
+        }
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(out, "}}").unwrap();
+}
+
+fn device_x(peripherals: &[Node]) -> String {
+    interrupts(peripherals)
+        .iter()
+        .map(|(name, _)| format!("PROVIDE({name} = DefaultHandler);\n"))
+        .collect()
+}
+
+fn generate_register_block(out: &mut String, peripheral: &Node) {
+    let name = text(peripheral, "name");
+
+    let registers: Vec<Node> = peripheral
+        .children()
+        .find(|node| node.has_tag_name("registers"))
+        .into_iter()
+        .flat_map(|registers| registers.children())
+        .filter(|node| node.has_tag_name("register"))
+        .collect();
+
+    // Only import what this block uses so the generated code doesn't have unused imports
+    let mut imports = vec!["RegisterSpec"];
+    if registers.iter().any(|register| has_fields(register)) {
+        imports.push("Field");
+    }
+    for access in ["ReadOnly", "ReadWrite", "WriteOnly"] {
+        if registers
+            .iter()
+            .any(|register| access_of(register) == access)
+        {
+            imports.push(access);
+        }
+    }
+
+    write_doc(out, peripheral);
+    writeln!(out, "pub mod {} {{", name.to_lowercase()).unwrap();
+    writeln!(out, "use crate::registers::{{{}}};", imports.join(", ")).unwrap();
+
+    for register in registers {
+        let register_name = identifier(&text(&register, "name"));
+        let offset = number(&text(&register, "addressOffset"));
+        let reset = optional_text(&register, "resetValue").map_or(0, |reset| number(&reset));
+        let access = access_of(&register);
+
+        write_doc(out, &register);
+        writeln!(out, "pub struct {register_name};").unwrap();
+        writeln!(out, "impl RegisterSpec for {register_name} {{").unwrap();
+        writeln!(out, "const OFFSET: u32 = {};", hex(offset)).unwrap();
+        writeln!(out, "const RESET: u32 = {};", hex(reset)).unwrap();
+        writeln!(out, "type Access = {access};").unwrap();
+        writeln!(out, "}}").unwrap();
+
+        if !has_fields(&register) {
+            continue;
+        }
+
+        writeln!(out, "impl {register_name} {{").unwrap();
+        for field in fields(&register) {
+            let field_name = identifier(&text(&field, "name"));
+            let offset = number(&text(&field, "bitOffset"));
+            let width = number(&text(&field, "bitWidth"));
+
+            write_doc(out, &field);
+            writeln!(
+                out,
+                "pub const {field_name}: Field<{register_name}, {width}> = Field::new({offset});"
+            )
+            .unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+    }
+
+    writeln!(out, "}}").unwrap();
+}
+
+fn fields<'a>(register: &Node<'a, 'a>) -> Vec<Node<'a, 'a>> {
+    register
+        .children()
+        .find(|node| node.has_tag_name("fields"))
+        .into_iter()
+        .flat_map(|fields| fields.children())
+        .filter(|node| node.has_tag_name("field"))
+        .collect()
+}
+
+fn has_fields(register: &Node) -> bool {
+    !fields(register).is_empty()
+}
+
+fn access_of(register: &Node) -> &'static str {
+    match optional_text(register, "access").as_deref() {
+        None | Some("read-write") => "ReadWrite",
+        Some("read-only") => "ReadOnly",
+        Some("write-only") => "WriteOnly",
+        Some(other) => panic!("unknown access {other:?}"),
+    }
+}
+
+fn optional_text(node: &Node, tag: &str) -> Option<String> {
+    node.children()
+        .find(|child| child.has_tag_name(tag))
+        .and_then(|child| child.text())
+        .map(|text| text.trim().to_string())
+}
+
+fn text(node: &Node, tag: &str) -> String {
+    optional_text(node, tag).unwrap_or_else(|| panic!("missing <{tag}>"))
+}
+
+fn number(text: &str) -> u32 {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).unwrap(),
+        None => text.parse().unwrap(),
+    }
+}
+
+fn hex(value: u32) -> String {
+    format!("0x{:04X}_{:04X}", value >> 16, value & 0xFFFF)
+}
+
+/// SVD names can start with a digit (like UART's 9BITADDR), which Rust identifiers can't
+fn identifier(name: &str) -> String {
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+fn write_doc(out: &mut String, node: &Node) {
+    if let Some(description) = optional_text(node, "description") {
+        let description = description.split_whitespace().collect::<Vec<_>>().join(" ");
+        writeln!(out, "#[doc = {description:?}]").unwrap();
+    }
+}
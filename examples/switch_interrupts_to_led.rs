@@ -0,0 +1,43 @@
+//! Like `switches_to_led`, but only looks at the switches when their interrupts say one has been
+//! pressed or released (and fades through the rainbow first)
+
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use driver_and_task_library::launchpad::setup_launchpad;
+use driver_and_task_library::rgb_led::Color;
+use driver_and_task_library::time::Duration;
+use driver_and_task_library::{BoardOptions, Clock, Trigger, H, L};
+
+#[entry]
+fn main() -> ! {
+    let mut launchpad = setup_launchpad(BoardOptions {
+        clock: Clock::Pll { divisor: 5 },
+    })
+    .unwrap();
+
+    launchpad.sw1.enable_interrupt(Trigger::BothEdges);
+    launchpad.sw2.enable_interrupt(Trigger::BothEdges);
+
+    // Fade through the rainbow once to show the LED works
+    for color in Color::RAINBOW {
+        launchpad.rgb_led.fade(color, Duration::from_millis(300));
+    }
+    launchpad.rgb_led.fade(Color::BLACK, Duration::from_millis(300));
+
+    loop {
+        // Only look at the switches again once one of them has been pressed or released
+        // (both are taken, so neither is left pending for the next time around)
+        if !(launchpad.sw1.take_interrupt() | launchpad.sw2.take_interrupt()) {
+            continue;
+        }
+
+        match [launchpad.sw2.read(), launchpad.sw1.read()] {
+            [L, L] => launchpad.rgb_led.set(Color::WHITE),
+            [L, H] => launchpad.rgb_led.set(Color::BLUE),
+            [H, L] => launchpad.rgb_led.set(Color::RED),
+            [H, H] => launchpad.rgb_led.set(Color::GREEN),
+        }
+    }
+}
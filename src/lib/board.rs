@@ -1,13 +1,24 @@
 //! Data sheet: https://www.ti.com/lit/ds/spms376e/spms376e.pdf
 
-use core::arch::asm;
+use core::sync::atomic::{AtomicBool, Ordering};
 
+use crate::clock::{setup_clock, Clock};
 use crate::gpio::ports::{
-    setup_port as setup_gpio_port, Port as GPIOPort, UsablePort as UsableGPIOPort,
+    setup_port as setup_gpio_port, Port as GPIOPort, PortOptions as GPIOPortOptions,
+    SetupError as GPIOSetupError, UsablePort as UsableGPIOPort,
 };
+use crate::pwm::{
+    setup_output as setup_pwm_output, Output as PWMOutput, OutputOptions as PWMOutputOptions,
+    SetupError as PWMSetupError, UsableOutput as UsablePWMOutput,
+};
+use crate::registers::sysctl::{
+    GPIOHBCTL, MISC, PLLSTAT, PRPWM, PRUART, RCC, RCC2, RCGCGPIO, RCGCPWM, RCGCUART, RIS,
+};
+use crate::registers::{peripherals, Register};
+use crate::time::{self, setup_systick};
 use crate::uart::{
     setup_port as setup_uart_port, Port as UARTPort, PortOptions as UARTPortOptions,
-    UsablePort as UsableUARTPort,
+    SetupError as UARTSetupError, UsablePort as UsableUARTPort,
 };
 
 /// The board
@@ -19,56 +30,284 @@ pub struct Board;
 impl Board {
     /// Page 231 of data sheet
     const fn base(&self) -> u32 {
-        0x400F_E000
+        peripherals::SYSCTL
+    }
+
+    /// The raw interrupt status (RIS) register, which has the PLL lock and oscillator power-up flags
+    ///
+    /// Page 244 of data sheet
+    pub(crate) const fn raw_interrupt_status(&self) -> Register<RIS> {
+        Register::new(self.base())
     }
 
-    /// The memory address of the GPIO Run mode clock gating control (RCGCGPIO) register for GPIO ports
+    /// The masked interrupt status and clear (MISC) register, where writing 1s clears the matching RIS flags
+    ///
+    /// Page 249 of data sheet
+    pub(crate) const fn masked_interrupt_status_and_clear(&self) -> Register<MISC> {
+        Register::new(self.base())
+    }
+
+    /// The run-mode clock configuration (RCC) register
+    ///
+    /// Page 254 of data sheet
+    pub(crate) const fn run_mode_clock_configuration(&self) -> Register<RCC> {
+        Register::new(self.base())
+    }
+
+    /// The run-mode clock configuration 2 (RCC2) register
+    ///
+    /// Page 260 of data sheet
+    pub(crate) const fn run_mode_clock_configuration_2(&self) -> Register<RCC2> {
+        Register::new(self.base())
+    }
+
+    /// The PLL status (PLLSTAT) register, which says whether the PLL is locked right now
+    ///
+    /// Page 283 of data sheet
+    pub(crate) const fn pll_status(&self) -> Register<PLLSTAT> {
+        Register::new(self.base())
+    }
+
+    /// The GPIO high-performance bus control (GPIOHBCTL) register, which picks the bus each GPIO port is on
+    ///
+    /// Page 258 of data sheet
+    pub(crate) const fn gpio_high_performance_bus_control(&self) -> Register<GPIOHBCTL> {
+        Register::new(self.base())
+    }
+
+    /// The GPIO Run mode clock gating control (RCGCGPIO) register for GPIO ports
     ///
     /// Page 340 of data sheet
-    pub(crate) const fn gpio_run_mode_clock_gating_control(&self) -> *mut u32 {
-        const OFFSET: u32 = 0x608;
-        (self.base() + OFFSET) as *mut u32
+    pub(crate) const fn gpio_run_mode_clock_gating_control(&self) -> Register<RCGCGPIO> {
+        Register::new(self.base())
+    }
+
+    /// The PWM Run mode clock gating control (RCGCPWM) register for PWM modules
+    ///
+    /// Page 354 of data sheet
+    pub(crate) const fn pwm_run_mode_clock_gating_control(&self) -> Register<RCGCPWM> {
+        Register::new(self.base())
+    }
+
+    /// The PWM peripheral ready (PRPWM) register, which says when a PWM module can be used after its clock is enabled
+    ///
+    /// Page 504 of data sheet
+    pub(crate) const fn pwm_peripheral_ready(&self) -> Register<PRPWM> {
+        Register::new(self.base())
+    }
+
+    /// The UART Run mode clock gating control (RCGCUART) register for UART ports
+    ///
+    /// Page 344 of data sheet
+    pub(crate) const fn uart_run_mode_clock_gating_control(&self) -> Register<RCGCUART> {
+        Register::new(self.base())
     }
 
-    /// The memory address of the run mode clock gating control 1 (RCGC1) register for the system
+    /// The UART peripheral ready (PRUART) register, which says when a UART port can be used after its clock is enabled
     ///
-    /// Page (ran out of time) of data sheet
-    pub(crate) const fn run_mode_clock_gating_control_1(&self) -> *mut u32 {
-        const OFFSET: u32 = 0x104;
-        (self.base() + OFFSET) as *mut u32
+    /// Page 502 of data sheet
+    pub(crate) const fn uart_peripheral_ready(&self) -> Register<PRUART> {
+        Register::new(self.base())
     }
 }
 
+pub struct BoardOptions {
+    pub clock: Clock,
+}
+
 /// A setup version of the board that GPIO and UART ports can be set up on
+///
+/// There's only ever one (see [`setup_board`]), and each port can only be claimed from it once
 pub struct UsableBoard {
     board: Board,
+    clock: Clock,
+    /// Bit `n` is set once GPIO port `n` has been claimed
+    claimed_gpio_ports: u8,
+    /// Bit `n` is set once UART port `n` has been claimed
+    claimed_uart_ports: u8,
+    /// The frequency each output of each PWM module was set up at (`None` until it's claimed)
+    pwm_output_frequencies: [[Option<u32>; 8]; 2],
+}
+
+impl UsableBoard {
+    /// The frequency the system clock was set up to run at
+    pub fn system_clock_hz(&self) -> u32 {
+        self.clock.hz()
+    }
+
+    /// Busy-wait for `millis` milliseconds (counted by SysTick)
+    pub fn delay_ms(&self, millis: u32) {
+        time::delay_ms(millis);
+    }
+
+    /// Busy-wait for `micros` microseconds (counted by SysTick)
+    pub fn delay_us(&self, micros: u32) {
+        time::delay_us(micros);
+    }
 }
 
 impl UsableBoard {
-    pub(crate) fn no_op(&self) {
-        unsafe {
-            asm!("nop");
+    /// Set up a GPIO port
+    ///
+    /// Fails if it's already been set up
+    pub fn setup_gpio_port(
+        &mut self,
+        port: GPIOPort,
+        options: GPIOPortOptions,
+    ) -> Result<UsableGPIOPort, GPIOSetupError> {
+        if !claim(&mut self.claimed_gpio_ports, port as u32) {
+            return Err(GPIOSetupError::AlreadySetUp);
         }
+
+        Ok(setup_gpio_port(self.board, port, options))
     }
 
-    pub(crate) fn no_ops(&self, n: u32) {
-        for _ in 0..n {
-            self.no_op();
+    /// Set up a UART port
+    ///
+    /// Fails if it's already been set up, or its baud rate can't be reached from the system clock
+    pub fn setup_uart_port(
+        &mut self,
+        port: UARTPort,
+        options: UARTPortOptions,
+    ) -> Result<UsableUARTPort, UARTSetupError> {
+        if self.claimed_uart_ports & (1 << port as u32) != 0 {
+            return Err(UARTSetupError::AlreadySetUp);
         }
+
+        let usable_port = setup_uart_port(self.board, port, options, self.system_clock_hz())?;
+
+        claim(&mut self.claimed_uart_ports, port as u32);
+        Ok(usable_port)
     }
 }
 
 impl UsableBoard {
-    pub fn setup_gpio_port(&mut self, port: GPIOPort) -> UsableGPIOPort {
-        setup_gpio_port(self.board, port)
-    }
+    /// Set up a PWM output
+    ///
+    /// Fails if it's already been set up, its frequency can't be reached from the system clock,
+    /// or the other output of its generator is already running at a different frequency.
+    /// The output's pin still has to be set up (with the output's function) to get the signal out
+    pub fn setup_pwm_output(
+        &mut self,
+        output: PWMOutput,
+        options: PWMOutputOptions,
+    ) -> Result<UsablePWMOutput, PWMSetupError> {
+        let frequencies = &mut self.pwm_output_frequencies[output.module() as usize];
+        if frequencies[output.number() as usize].is_some() {
+            return Err(PWMSetupError::AlreadySetUp);
+        }
+
+        let frequency_hz = options.frequency_hz;
+        let usable_output = setup_pwm_output(
+            self.board,
+            output,
+            options,
+            self.clock.hz(),
+            frequencies[output.sibling().number() as usize],
+        )?;
 
-    pub fn setup_uart_port(&mut self, port: UARTPort, options: UARTPortOptions) -> UsableUARTPort {
-        setup_uart_port(self.board, port, options, &|n| self.no_ops(n))
+        frequencies[output.number() as usize] = Some(frequency_hz);
+        Ok(usable_output)
     }
 }
 
+/// Mark bit `bit` of `claimed` as claimed, returning whether it wasn't already
+fn claim(claimed: &mut u8, bit: u32) -> bool {
+    let already_claimed = *claimed & (1 << bit) != 0;
+    *claimed |= 1 << bit;
+
+    !already_claimed
+}
+
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
 /// Start using the driver and task library by setting up the TM4C123GXL board
-pub fn setup_board() -> UsableBoard {
-    UsableBoard { board: Board }
+///
+/// This only works once: every call after the first returns `None`
+pub fn setup_board(options: BoardOptions) -> Option<UsableBoard> {
+    if TAKEN.swap(true, Ordering::Relaxed) {
+        return None;
+    }
+
+    Some(unsafe { steal_board(options) })
+}
+
+/// Set up the board whether or not it has already been set up
+///
+/// This is for fault handlers (like the panic handler) that have to take over the board
+/// no matter what the rest of the program was doing with it
+///
+/// # Safety
+///
+/// Any ports already claimed from the existing board can be claimed again from this one,
+/// so the caller must make sure the existing board and its ports are never used again
+pub unsafe fn steal_board(options: BoardOptions) -> UsableBoard {
+    let board = Board;
+
+    setup_clock(board, options.clock);
+    setup_systick(options.clock.hz());
+
+    UsableBoard {
+        board,
+        clock: options.clock,
+        claimed_gpio_ports: 0,
+        claimed_uart_ports: 0,
+        pwm_output_frequencies: [[None; 8]; 2],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses_match_the_data_sheet() {
+        assert_eq!(Board.base(), 0x400F_E000);
+        assert_eq!(
+            Board.gpio_run_mode_clock_gating_control().address() as u32,
+            0x400F_E608
+        );
+        assert_eq!(Board.raw_interrupt_status().address() as u32, 0x400F_E050);
+        assert_eq!(
+            Board.masked_interrupt_status_and_clear().address() as u32,
+            0x400F_E058
+        );
+        assert_eq!(
+            Board.run_mode_clock_configuration().address() as u32,
+            0x400F_E060
+        );
+        assert_eq!(
+            Board.run_mode_clock_configuration_2().address() as u32,
+            0x400F_E070
+        );
+        assert_eq!(Board.pll_status().address() as u32, 0x400F_E168);
+    }
+
+    const OPTIONS: BoardOptions = BoardOptions {
+        clock: Clock::PrecisionInternalOscillator,
+    };
+
+    #[test]
+    fn the_board_can_only_be_set_up_once() {
+        assert!(setup_board(OPTIONS).is_some());
+        assert!(setup_board(OPTIONS).is_none());
+    }
+
+    #[test]
+    fn each_port_can_only_be_claimed_once() {
+        let mut board = unsafe { steal_board(OPTIONS) };
+
+        assert!(board
+            .setup_gpio_port(GPIOPort::F, GPIOPortOptions::default())
+            .is_ok());
+        assert_eq!(
+            board
+                .setup_gpio_port(GPIOPort::F, GPIOPortOptions::default())
+                .err(),
+            Some(GPIOSetupError::AlreadySetUp)
+        );
+        assert!(board
+            .setup_gpio_port(GPIOPort::A, GPIOPortOptions::default())
+            .is_ok());
+    }
 }
@@ -0,0 +1,291 @@
+//! Debounced buttons that turn presses into events
+//!
+//! A switch's contacts bounce for a few milliseconds when it's pressed or released, so a raw reading flips
+//! back and forth. A [`Button`] only believes a new level once it's held steady for the debounce time,
+//! then works out clicks, double clicks, and long presses from how long it's held and how soon it's pressed again
+
+use crate::time::{Duration, Instant};
+use crate::ReadablePin;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The button went down
+    Press,
+    /// The button came back up
+    Release,
+    /// The button was pressed and released once (reported once the double click time has passed without another press)
+    Click,
+    /// The button was clicked twice within the double click time
+    DoubleClick,
+    /// The button has been held down for the long press time (it's still down, and won't also be a click)
+    LongPress,
+}
+
+pub struct ButtonOptions {
+    /// Whether the pin reads low while the button is pressed (like the LaunchPad's switches, which use `Pull::Up`)
+    pub active_low: bool,
+    /// How long the pin has to stay at a new level before it's believed
+    pub debounce_ms: u32,
+    /// How long the button has to be held down for a long press
+    pub long_press_ms: u32,
+    /// How soon a second click has to start for a double click
+    pub double_click_ms: u32,
+}
+
+impl Default for ButtonOptions {
+    /// An active-low button (like the LaunchPad's switches)
+    fn default() -> Self {
+        ButtonOptions {
+            active_low: true,
+            debounce_ms: 20,
+            long_press_ms: 1000,
+            double_click_ms: 300,
+        }
+    }
+}
+
+pub struct Button {
+    pin: ReadablePin,
+    active_low: bool,
+    debounce: Duration,
+    long_press: Duration,
+    double_click: Duration,
+    /// The last raw reading, and when it started
+    raw: bool,
+    raw_since: Instant,
+    /// The debounced state, and when it started
+    pressed: bool,
+    pressed_since: Instant,
+    /// Whether this press has already been reported as a long press
+    long_pressed: bool,
+    /// When the last click ended, if it could still become a double click
+    clicked_at: Option<Instant>,
+    /// An event that happened at the same time as the one last returned
+    queued: Option<Event>,
+}
+
+impl Button {
+    pub fn new(pin: ReadablePin, options: ButtonOptions) -> Self {
+        let now = Instant::now();
+        let mut button = Button {
+            pin,
+            active_low: options.active_low,
+            debounce: Duration::from_millis(options.debounce_ms as u64),
+            long_press: Duration::from_millis(options.long_press_ms as u64),
+            double_click: Duration::from_millis(options.double_click_ms as u64),
+            raw: false,
+            raw_since: now,
+            pressed: false,
+            pressed_since: now,
+            long_pressed: false,
+            clicked_at: None,
+            queued: None,
+        };
+
+        // A button that's already down when it's set up isn't a press
+        button.raw = button.read();
+        button.pressed = button.raw;
+        button.long_pressed = button.raw;
+        button
+    }
+
+    /// Give back the pin
+    pub fn into_pin(self) -> ReadablePin {
+        self.pin
+    }
+
+    /// Whether the button is down (debounced)
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Check on the button, returning what's happened since it was last checked
+    ///
+    /// Has to be called at least as often as the debounce time (and more often for better timing),
+    /// until it returns `None`
+    pub fn poll(&mut self) -> Option<Event> {
+        self.update(Instant::now())
+    }
+
+    /// Like [`Button::poll`], but at a time the caller already has (like from a periodic tick)
+    pub fn update(&mut self, now: Instant) -> Option<Event> {
+        if let Some(event) = self.queued.take() {
+            return Some(event);
+        }
+
+        let raw = self.read();
+        if raw != self.raw {
+            self.raw = raw;
+            self.raw_since = now;
+        }
+
+        if self.raw != self.pressed && now.duration_since(self.raw_since) >= self.debounce {
+            self.pressed = self.raw;
+            self.pressed_since = now;
+
+            return Some(if self.pressed {
+                self.press(now)
+            } else {
+                self.release(now)
+            });
+        }
+
+        if self.pressed
+            && !self.long_pressed
+            && now.duration_since(self.pressed_since) >= self.long_press
+        {
+            self.long_pressed = true;
+            // A long press can't be the start of a double click
+            self.clicked_at = None;
+            return Some(Event::LongPress);
+        }
+
+        if let Some(clicked_at) = self.clicked_at {
+            if !self.pressed && now.duration_since(clicked_at) >= self.double_click {
+                self.clicked_at = None;
+                return Some(Event::Click);
+            }
+        }
+
+        None
+    }
+
+    fn press(&mut self, now: Instant) -> Event {
+        self.long_pressed = false;
+
+        // Too late for the last click to become a double click
+        if let Some(clicked_at) = self.clicked_at {
+            if now.duration_since(clicked_at) >= self.double_click {
+                self.clicked_at = None;
+                self.queued = Some(Event::Press);
+                return Event::Click;
+            }
+        }
+
+        Event::Press
+    }
+
+    fn release(&mut self, now: Instant) -> Event {
+        if !self.long_pressed {
+            match self.clicked_at.take() {
+                Some(_) => self.queued = Some(Event::DoubleClick),
+                None => self.clicked_at = Some(now),
+            }
+        }
+
+        Event::Release
+    }
+
+    fn read(&self) -> bool {
+        self.pin.read() != self.active_low
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpio::pins::steal_readable_pins;
+    use crate::gpio::ports::Port;
+    use crate::memory::simulated;
+    use crate::{Function, Pin, Pull, ReadablePinOptions};
+
+    /// The DATA alias for pin 4 of port F
+    const DATA: *mut u32 = (0x4002_5000 + (1 << (4 + 2))) as *mut u32;
+
+    fn button() -> Button {
+        // Not pressed (high) to start with
+        simulated::with(|memory| memory.preset(DATA, 1 << 4));
+
+        let [pin] = steal_readable_pins(
+            Port::F,
+            [Pin::Four],
+            ReadablePinOptions {
+                function: Function::Digital,
+                pull: Pull::Up,
+            },
+        )
+        .unwrap()
+        .into_pins();
+
+        Button::new(pin, ButtonOptions::default())
+    }
+
+    fn set_pressed(pressed: bool) {
+        simulated::with(|memory| memory.preset(DATA, if pressed { 0 } else { 1 << 4 }));
+    }
+
+    /// Step through `levels`, one millisecond each, collecting every event
+    fn run(button: &mut Button, start: Instant, levels: &[(bool, u64)]) -> Vec<Event> {
+        let mut events = Vec::new();
+        let mut millis = 0;
+
+        for &(pressed, duration) in levels {
+            set_pressed(pressed);
+
+            for _ in 0..duration {
+                millis += 1;
+                while let Some(event) = button.update(start + Duration::from_millis(millis)) {
+                    events.push(event);
+                }
+            }
+        }
+
+        events
+    }
+
+    #[test]
+    fn bounces_are_ignored_and_single_clicks_wait_for_the_double_click_time() {
+        let mut button = button();
+        let start = Instant::now();
+
+        let events = run(
+            &mut button,
+            start,
+            &[
+                (true, 2),
+                (false, 3),
+                (true, 100),
+                (false, 1),
+                (true, 1),
+                (false, 299),
+            ],
+        );
+        assert_eq!(events, [Event::Press, Event::Release]);
+
+        let events = run(
+            &mut button,
+            start + Duration::from_millis(406),
+            &[(false, 30)],
+        );
+        assert_eq!(events, [Event::Click]);
+    }
+
+    #[test]
+    fn double_clicks_and_long_presses() {
+        let mut button = button();
+        let start = Instant::now();
+
+        let events = run(
+            &mut button,
+            start,
+            &[(true, 50), (false, 100), (true, 50), (false, 400)],
+        );
+        assert_eq!(
+            events,
+            [
+                Event::Press,
+                Event::Release,
+                Event::Press,
+                Event::Release,
+                Event::DoubleClick
+            ]
+        );
+
+        let events = run(
+            &mut button,
+            start + Duration::from_millis(600),
+            &[(true, 1100), (false, 400)],
+        );
+        assert_eq!(events, [Event::Press, Event::LongPress, Event::Release]);
+    }
+}
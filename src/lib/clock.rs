@@ -0,0 +1,223 @@
+//! Choosing and configuring the system clock
+//!
+//! Page 219 of data sheet (Clock Control)
+
+use crate::registers::sysctl::{MISC, PLLSTAT, RCC, RCC2, RIS};
+use crate::registers::{Bits, FieldValue};
+use crate::Board;
+
+/// Both the precision internal oscillator and the LaunchPad's crystal run at 16 MHz
+const OSCILLATOR_HZ: u32 = 16_000_000;
+
+/// The PLL's output when DIV400 is set (before the system clock divider)
+const PLL_HZ: u32 = 400_000_000;
+
+/// The system clock has to stay at or under 80 MHz
+const MIN_PLL_DIVISOR: u32 = PLL_HZ / 80_000_000;
+/// SYSDIV2 and SYSDIV2LSB together hold the divisor minus 1 in 7 bits
+const MAX_PLL_DIVISOR: u32 = 128;
+
+/// Where the system clock comes from
+#[derive(Clone, Copy)]
+pub enum Clock {
+    /// The 16 MHz precision internal oscillator (PIOSC), which is what the chip starts up on
+    PrecisionInternalOscillator,
+    /// The main oscillator (MOSC), driven by the LaunchPad's 16 MHz crystal
+    MainOscillator,
+    /// The 400 MHz PLL (driven by the main oscillator) divided by `divisor`
+    ///
+    /// `divisor` has to be 5 through 128, so a divisor of 5 is the fastest the chip can run: 80 MHz
+    Pll { divisor: u32 },
+}
+
+impl Clock {
+    /// The frequency the system clock runs at with this clock
+    pub const fn hz(&self) -> u32 {
+        match self {
+            Clock::PrecisionInternalOscillator | Clock::MainOscillator => OSCILLATOR_HZ,
+            Clock::Pll { divisor } => PLL_HZ / *divisor,
+        }
+    }
+}
+
+/// Page 256 of data sheet (XTAL field encodings)
+const XTAL_16_MHZ: u32 = 0x15;
+
+/// Page 261 of data sheet (OSCSRC2 field encodings)
+#[derive(Clone, Copy)]
+enum OscillatorSource {
+    Main,
+    PrecisionInternal,
+}
+
+impl FieldValue<3> for OscillatorSource {
+    fn into_bits(self) -> u32 {
+        match self {
+            OscillatorSource::Main => 0x0,
+            OscillatorSource::PrecisionInternal => 0x1,
+        }
+    }
+    fn from_bits(bits: u32) -> Self {
+        match bits {
+            0x0 => OscillatorSource::Main,
+            _ => OscillatorSource::PrecisionInternal,
+        }
+    }
+}
+
+/// Page 231 of data sheet (Main PLL Frequency Configuration)
+pub(crate) fn setup_clock(board: Board, clock: Clock) {
+    if let Clock::Pll { divisor } = clock {
+        assert!(
+            (MIN_PLL_DIVISOR..=MAX_PLL_DIVISOR).contains(&divisor),
+            "the PLL divisor must be 5 through 128"
+        );
+    }
+
+    let rcc = board.run_mode_clock_configuration();
+    let rcc2 = board.run_mode_clock_configuration_2();
+    let raw_interrupt_status = board.raw_interrupt_status();
+
+    let source = match clock {
+        Clock::PrecisionInternalOscillator => OscillatorSource::PrecisionInternal,
+        Clock::MainOscillator | Clock::Pll { .. } => OscillatorSource::Main,
+    };
+
+    // The flag stays set from any earlier setup, so clear it before waiting on it again
+    board
+        .masked_interrupt_status_and_clear()
+        .write(MISC::MOSCPUPMIS.mask());
+
+    // Use RCC2 (which has the wider SYSDIV2 and DIV400) instead of RCC where they overlap
+    rcc2.modify(RCC2::USERCC2, true);
+
+    // Run straight from the oscillator while everything is being changed
+    rcc2.modify(RCC2::BYPASS2, true);
+    rcc.modify(RCC::USESYSDIV, false);
+
+    if let OscillatorSource::Main = source {
+        // Tell the chip what crystal is attached, then power the main oscillator up
+        rcc.modify(RCC::XTAL, Bits::<5>::new(XTAL_16_MHZ));
+        let powered_down = rcc.read_field::<1, bool>(RCC::MOSCDIS);
+        rcc.modify(RCC::MOSCDIS, false);
+
+        // The power-up flag is only raised when the main oscillator actually powers up,
+        // so there's nothing to wait for if an earlier setup already powered it
+        if powered_down {
+            while !raw_interrupt_status.read_field::<1, bool>(RIS::MOSCPUPRIS) {}
+        }
+    }
+
+    rcc2.modify(RCC2::OSCSRC2, source);
+
+    let Clock::Pll { divisor } = clock else {
+        // The PLL isn't needed, so keep it off
+        rcc2.modify(RCC2::PWRDN2, true);
+        return;
+    };
+
+    rcc2.modify(RCC2::PWRDN2, false);
+
+    // Divide the 400 MHz PLL output directly, using SYSDIV2LSB as an extra bit of the divisor
+    rcc2.modify(RCC2::DIV400, true);
+    rcc2.modify(RCC2::SYSDIV2, Bits::<6>::new((divisor - 1) >> 1));
+    rcc2.modify(RCC2::SYSDIV2LSB, (divisor - 1) & 1 != 0);
+    rcc.modify(RCC::USESYSDIV, true);
+
+    // Wait for the PLL to lock before switching over to it (PLLSTAT says whether it's locked now,
+    // whereas the lock flag in RIS is only raised when it locks, and it may have locked in an earlier setup)
+    while !board.pll_status().read_field::<1, bool>(PLLSTAT::LOCK) {}
+
+    rcc2.modify(RCC2::BYPASS2, false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::simulated;
+    use crate::registers::RegisterSpec;
+
+    #[test]
+    fn pll_at_80_mhz() {
+        let board = Board;
+        simulated::with(|memory| {
+            // Straight out of reset, with the main oscillator powered down
+            memory.preset(board.run_mode_clock_configuration().address(), RCC::RESET);
+            memory.preset(
+                board.raw_interrupt_status().address(),
+                RIS::MOSCPUPRIS.mask(),
+            );
+            memory.preset(board.pll_status().address(), PLLSTAT::LOCK.mask());
+        });
+
+        let clock = Clock::Pll { divisor: 5 };
+        setup_clock(board, clock);
+
+        assert_eq!(clock.hz(), 80_000_000);
+
+        let rcc = board.run_mode_clock_configuration();
+        let rcc2 = board.run_mode_clock_configuration_2();
+
+        assert!(rcc2.read_field::<1, bool>(RCC2::USERCC2));
+        assert!(rcc2.read_field::<1, bool>(RCC2::DIV400));
+        assert_eq!(rcc2.read_field::<6, Bits<6>>(RCC2::SYSDIV2).value(), 2);
+        assert!(!rcc2.read_field::<1, bool>(RCC2::SYSDIV2LSB));
+        assert!(!rcc2.read_field::<1, bool>(RCC2::PWRDN2));
+        assert!(!rcc2.read_field::<1, bool>(RCC2::BYPASS2));
+        assert!(rcc.read_field::<1, bool>(RCC::USESYSDIV));
+        assert_eq!(rcc.read_field::<5, Bits<5>>(RCC::XTAL).value(), XTAL_16_MHZ);
+
+        assert!(!rcc.read_field::<1, bool>(RCC::MOSCDIS));
+
+        // The flag is cleared before anything is changed, and the PLL is only switched to at the very end, once it's locked
+        simulated::with(|memory| {
+            assert_eq!(
+                memory.writes().first().unwrap(),
+                &(
+                    board.masked_interrupt_status_and_clear().address(),
+                    MISC::MOSCPUPMIS.mask()
+                ),
+                "the power-up flag should be cleared first"
+            );
+            assert_eq!(
+                memory.writes().last().unwrap().0,
+                rcc2.address(),
+                "BYPASS2 should be cleared last"
+            );
+        });
+    }
+
+    #[test]
+    fn setting_up_again_doesnt_wait_for_flags_that_wont_be_raised_again() {
+        let board = Board;
+        // The main oscillator is already powered and the PLL already locked, so neither flag is raised
+        simulated::with(|memory| {
+            memory.preset(board.raw_interrupt_status().address(), 0);
+            memory.preset(board.pll_status().address(), PLLSTAT::LOCK.mask());
+        });
+
+        setup_clock(board, Clock::Pll { divisor: 5 });
+        setup_clock(board, Clock::Pll { divisor: 10 });
+
+        let rcc2 = board.run_mode_clock_configuration_2();
+        assert_eq!(rcc2.read_field::<6, Bits<6>>(RCC2::SYSDIV2).value(), 4);
+        assert!(rcc2.read_field::<1, bool>(RCC2::SYSDIV2LSB));
+    }
+
+    #[test]
+    fn precision_internal_oscillator_powers_the_pll_down() {
+        let board = Board;
+        setup_clock(board, Clock::PrecisionInternalOscillator);
+
+        let rcc2 = board.run_mode_clock_configuration_2();
+        assert!(rcc2.read_field::<1, bool>(RCC2::PWRDN2));
+        assert!(rcc2.read_field::<1, bool>(RCC2::BYPASS2));
+        assert_eq!(Clock::PrecisionInternalOscillator.hz(), 16_000_000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn pll_cant_go_over_80_mhz() {
+        setup_clock(Board, Clock::Pll { divisor: 4 });
+    }
+}
@@ -0,0 +1,135 @@
+//! Writing several pins of a port as one value
+//!
+//! The DATA register has an alias for every combination of pins, and a store to one of them only changes
+//! the pins in its address mask (page 654 of data sheet, Data Register Operation). So a whole value can go out
+//! in a single store, with no read-modify-write that other pins of the port (or an interrupt) could race with
+
+use super::pins::WritablePins;
+
+/// Which of the bus's pins gets the value's least significant bit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitOrder {
+    /// The first pin the pins were set up with gets bit 0
+    LeastSignificantFirst,
+    /// The first pin the pins were set up with gets bit `N - 1`
+    MostSignificantFirst,
+}
+
+/// `N` writable pins of one port driven together as an `N`-bit value (for LCD data buses, DAC ladders, ...)
+///
+/// The pins don't have to be next to each other or in order
+pub struct ParallelBus<const N: usize> {
+    pins: WritablePins<N>,
+    /// The pin that each bit of a value goes to, least significant bit first
+    pin_bits: [u32; N],
+    /// When the pins are next to each other and in order, how far a value has to be shifted to line up with them
+    shift: Option<u32>,
+}
+
+impl<const N: usize> ParallelBus<N> {
+    pub fn new(pins: WritablePins<N>, order: BitOrder) -> Self {
+        let mut pin_bits = pins.pins.each_ref().map(|pin| pin.pin as u32);
+        if let BitOrder::MostSignificantFirst = order {
+            pin_bits.reverse();
+        }
+
+        let shift = pin_bits
+            .windows(2)
+            .all(|pair| pair[1] == pair[0] + 1)
+            .then(|| pin_bits.first().copied().unwrap_or(0));
+
+        ParallelBus {
+            pins,
+            pin_bits,
+            shift,
+        }
+    }
+
+    /// Give back the pins, to be used one by one again
+    pub fn into_pins(self) -> WritablePins<N> {
+        self.pins
+    }
+
+    /// Drive the pins with the low `N` bits of `value` (the rest are ignored) in a single store
+    pub fn write(&mut self, value: u32) {
+        // Bits outside the alias's mask are ignored by the hardware, so there's no need to mask them off here
+        self.pins.data.write(self.to_word(value));
+    }
+
+    /// The value the pins are currently at
+    pub fn read(&self) -> u32 {
+        self.to_value(self.pins.data.read())
+    }
+
+    fn to_word(&self, value: u32) -> u32 {
+        match self.shift {
+            Some(shift) => value << shift,
+            None => self
+                .pin_bits
+                .iter()
+                .enumerate()
+                .fold(0, |word, (bit, pin_bit)| {
+                    word | ((value >> bit) & 1) << pin_bit
+                }),
+        }
+    }
+
+    fn to_value(&self, word: u32) -> u32 {
+        match self.shift {
+            Some(shift) => (word >> shift) & ((1 << N) - 1),
+            None => self
+                .pin_bits
+                .iter()
+                .enumerate()
+                .fold(0, |value, (bit, pin_bit)| {
+                    value | ((word >> pin_bit) & 1) << bit
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpio::pins::steal_writable_pins;
+    use crate::gpio::ports::Port;
+    use crate::memory::simulated::{self, Access};
+    use crate::{Pin, WritablePinOptions};
+
+    #[test]
+    fn contiguous_pins_are_written_in_one_store() {
+        let pins = [Pin::Four, Pin::Five, Pin::Six, Pin::Seven];
+        let mut bus = ParallelBus::new(
+            steal_writable_pins(Port::B, pins, WritablePinOptions::default()).unwrap(),
+            BitOrder::LeastSignificantFirst,
+        );
+        let data = Port::B.apb().data(&pins).address();
+        simulated::with(|memory| memory.clear_accesses());
+
+        bus.write(0b1010);
+
+        simulated::with(|memory| {
+            assert_eq!(memory.accesses(), [Access::Write(data, 0b1010_0000)]);
+        });
+        assert_eq!(bus.read(), 0b1010);
+    }
+
+    #[test]
+    fn scattered_pins_follow_the_bit_order() {
+        let pins = [Pin::Zero, Pin::Three, Pin::Six];
+        let mut bus = ParallelBus::new(
+            steal_writable_pins(Port::D, pins, WritablePinOptions::default()).unwrap(),
+            BitOrder::MostSignificantFirst,
+        );
+        let data = Port::D.apb().data(&pins).address();
+        simulated::with(|memory| memory.clear_accesses());
+
+        // Bit 2 goes to pin 0 and bit 0 goes to pin 6
+        bus.write(0b011);
+
+        simulated::with(|memory| {
+            assert_eq!(memory.writes(), [(data, 0b0100_1000)]);
+        });
+        assert_eq!(bus.read(), 0b011);
+    }
+}
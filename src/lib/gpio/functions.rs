@@ -0,0 +1,393 @@
+//! What each pin can be used for
+//!
+//! Table 23-5 on page 1351 of data sheet (GPIO Pins and Alternate Functions)
+
+// Signals are named exactly as they are in the data sheet
+#![allow(clippy::upper_case_acronyms)]
+
+use crate::registers::Bits;
+
+use super::pins::Pin;
+use super::ports::Port;
+
+/// What a pin is used for: plain digital input / output, analog, or one of the signals
+/// of the peripherals that can be muxed onto it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Function {
+    /// An analog signal (ADC input, analog comparator input, or USB analog signal)
+    Analog,
+    /// Plain digital input / output, controlled through the DATA register
+    Digital,
+
+    // UART
+    U0Rx,
+    U0Tx,
+    U1Rx,
+    U1Tx,
+    U1RTS,
+    U1CTS,
+    U2Rx,
+    U2Tx,
+    U3Rx,
+    U3Tx,
+    U4Rx,
+    U4Tx,
+    U5Rx,
+    U5Tx,
+    U6Rx,
+    U6Tx,
+    U7Rx,
+    U7Tx,
+
+    // SSI
+    SSI0Clk,
+    SSI0Fss,
+    SSI0Rx,
+    SSI0Tx,
+    SSI1Clk,
+    SSI1Fss,
+    SSI1Rx,
+    SSI1Tx,
+    SSI2Clk,
+    SSI2Fss,
+    SSI2Rx,
+    SSI2Tx,
+    SSI3Clk,
+    SSI3Fss,
+    SSI3Rx,
+    SSI3Tx,
+
+    // I2C
+    I2C0SCL,
+    I2C0SDA,
+    I2C1SCL,
+    I2C1SDA,
+    I2C2SCL,
+    I2C2SDA,
+    I2C3SCL,
+    I2C3SDA,
+
+    // PWM
+    M0PWM0,
+    M0PWM1,
+    M0PWM2,
+    M0PWM3,
+    M0PWM4,
+    M0PWM5,
+    M0PWM6,
+    M0PWM7,
+    M0FAULT0,
+    M1PWM0,
+    M1PWM1,
+    M1PWM2,
+    M1PWM3,
+    M1PWM4,
+    M1PWM5,
+    M1PWM6,
+    M1PWM7,
+    M1FAULT0,
+
+    // CAN
+    CAN0Rx,
+    CAN0Tx,
+    CAN1Rx,
+    CAN1Tx,
+
+    // 16/32-bit timers
+    T0CCP0,
+    T0CCP1,
+    T1CCP0,
+    T1CCP1,
+    T2CCP0,
+    T2CCP1,
+    T3CCP0,
+    T3CCP1,
+    T4CCP0,
+    T4CCP1,
+    T5CCP0,
+    T5CCP1,
+
+    // 32/64-bit wide timers
+    WT0CCP0,
+    WT0CCP1,
+    WT1CCP0,
+    WT1CCP1,
+    WT2CCP0,
+    WT2CCP1,
+    WT3CCP0,
+    WT3CCP1,
+    WT4CCP0,
+    WT4CCP1,
+    WT5CCP0,
+    WT5CCP1,
+
+    // Quadrature encoder interfaces
+    IDX0,
+    PhA0,
+    PhB0,
+    IDX1,
+    PhA1,
+    PhB1,
+
+    // USB
+    USB0EPEN,
+    USB0PFLT,
+
+    // Analog comparator outputs
+    C0o,
+    C1o,
+
+    // Core
+    NMI,
+    TCK,
+    TMS,
+    TDI,
+    TDO,
+    TRCLK,
+    TRD0,
+    TRD1,
+}
+
+impl Function {
+    /// Whether the pin has to be open drain (GPIOODR) for this function
+    ///
+    /// Page 676 of data sheet: I2C data pins are open drain, but I2C clock pins must not be
+    pub(crate) const fn open_drain(&self) -> bool {
+        matches!(
+            self,
+            Function::I2C0SDA | Function::I2C1SDA | Function::I2C2SDA | Function::I2C3SDA
+        )
+    }
+}
+
+/// How a pin has to be set up for a particular [`Function`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Mux {
+    /// Analog mode (AMSEL) instead of digital
+    Analog,
+    /// Plain digital input / output (no alternate function)
+    Digital,
+    /// The alternate function selected by this value in the pin's PMCx field of GPIOPCTL
+    Alternate(Bits<4>),
+}
+
+/// How `pin` of `port` has to be set up for `function`, or `None` if the pin doesn't have that function
+pub(crate) fn mux(port: Port, pin: Pin, function: Function) -> Option<Mux> {
+    if !exists(port, pin) {
+        return None;
+    }
+
+    match function {
+        Function::Digital => Some(Mux::Digital),
+        Function::Analog => has_analog_function(port, pin).then_some(Mux::Analog),
+        _ => alternate_functions(port, pin)
+            .iter()
+            .find(|(alternate, _)| *alternate == function)
+            .map(|(_, port_control)| Mux::Alternate(Bits::new(*port_control))),
+    }
+}
+
+/// Port E only has 6 pins and port F only has 5
+const fn exists(port: Port, pin: Pin) -> bool {
+    match port {
+        Port::E => (pin as u32) < 6,
+        Port::F => (pin as u32) < 5,
+        Port::A | Port::B | Port::C | Port::D => true,
+    }
+}
+
+/// Whether the pin has an analog signal (in the Analog Function column of Table 23-5)
+const fn has_analog_function(port: Port, pin: Pin) -> bool {
+    use Pin::*;
+
+    match port {
+        // USB0ID, USB0VBUS, AIN10, AIN11
+        Port::B => matches!(pin, Zero | One | Four | Five),
+        // C1-, C1+, C0+, C0-
+        Port::C => matches!(pin, Four | Five | Six | Seven),
+        // AIN7 to AIN4, USB0DM, USB0DP
+        Port::D => matches!(pin, Zero | One | Two | Three | Four | Five),
+        // AIN3 to AIN0, AIN9, AIN8
+        Port::E => matches!(pin, Zero | One | Two | Three | Four | Five),
+        Port::A | Port::F => false,
+    }
+}
+
+/// The alternate (digital) functions of the pin, each with its PMCx value
+/// (the Digital Function (GPIOPCTL PMCx Bit Field Encoding) columns of Table 23-5)
+const fn alternate_functions(port: Port, pin: Pin) -> &'static [(Function, u32)] {
+    use Function::*;
+    use Pin::*;
+
+    match (port, pin) {
+        (Port::A, Zero) => &[(U0Rx, 1), (CAN1Rx, 8)],
+        (Port::A, One) => &[(U0Tx, 1), (CAN1Tx, 8)],
+        (Port::A, Two) => &[(SSI0Clk, 2)],
+        (Port::A, Three) => &[(SSI0Fss, 2)],
+        (Port::A, Four) => &[(SSI0Rx, 2)],
+        (Port::A, Five) => &[(SSI0Tx, 2)],
+        (Port::A, Six) => &[(I2C1SCL, 3), (M1PWM2, 5)],
+        (Port::A, Seven) => &[(I2C1SDA, 3), (M1PWM3, 5)],
+
+        (Port::B, Zero) => &[(U1Rx, 1), (T2CCP0, 7)],
+        (Port::B, One) => &[(U1Tx, 1), (T2CCP1, 7)],
+        (Port::B, Two) => &[(I2C0SCL, 3), (T3CCP0, 7)],
+        (Port::B, Three) => &[(I2C0SDA, 3), (T3CCP1, 7)],
+        (Port::B, Four) => &[(SSI2Clk, 2), (M0PWM2, 4), (T1CCP0, 7), (CAN0Rx, 8)],
+        (Port::B, Five) => &[(SSI2Fss, 2), (M0PWM3, 4), (T1CCP1, 7), (CAN0Tx, 8)],
+        (Port::B, Six) => &[(SSI2Rx, 2), (M0PWM0, 4), (T0CCP0, 7)],
+        (Port::B, Seven) => &[(SSI2Tx, 2), (M0PWM1, 4), (T0CCP1, 7)],
+
+        (Port::C, Zero) => &[(TCK, 1), (T4CCP0, 7)],
+        (Port::C, One) => &[(TMS, 1), (T4CCP1, 7)],
+        (Port::C, Two) => &[(TDI, 1), (T5CCP0, 7)],
+        (Port::C, Three) => &[(TDO, 1), (T5CCP1, 7)],
+        (Port::C, Four) => &[
+            (U4Rx, 1),
+            (U1Rx, 2),
+            (M0PWM6, 4),
+            (IDX1, 6),
+            (WT0CCP0, 7),
+            (U1RTS, 8),
+        ],
+        (Port::C, Five) => &[
+            (U4Tx, 1),
+            (U1Tx, 2),
+            (M0PWM7, 4),
+            (PhA1, 6),
+            (WT0CCP1, 7),
+            (U1CTS, 8),
+        ],
+        (Port::C, Six) => &[(U3Rx, 1), (PhB1, 6), (WT1CCP0, 7), (USB0EPEN, 8)],
+        (Port::C, Seven) => &[(U3Tx, 1), (WT1CCP1, 7), (USB0PFLT, 8)],
+
+        (Port::D, Zero) => &[
+            (SSI3Clk, 1),
+            (SSI1Clk, 2),
+            (I2C3SCL, 3),
+            (M0PWM6, 4),
+            (M1PWM0, 5),
+            (WT2CCP0, 7),
+        ],
+        (Port::D, One) => &[
+            (SSI3Fss, 1),
+            (SSI1Fss, 2),
+            (I2C3SDA, 3),
+            (M0PWM7, 4),
+            (M1PWM1, 5),
+            (WT2CCP1, 7),
+        ],
+        (Port::D, Two) => &[
+            (SSI3Rx, 1),
+            (SSI1Rx, 2),
+            (M0FAULT0, 4),
+            (WT3CCP0, 7),
+            (USB0EPEN, 8),
+        ],
+        (Port::D, Three) => &[
+            (SSI3Tx, 1),
+            (SSI1Tx, 2),
+            (IDX0, 6),
+            (WT3CCP1, 7),
+            (USB0PFLT, 8),
+        ],
+        (Port::D, Four) => &[(U6Rx, 1), (WT4CCP0, 7)],
+        (Port::D, Five) => &[(U6Tx, 1), (WT4CCP1, 7)],
+        (Port::D, Six) => &[(U2Rx, 1), (M0FAULT0, 4), (PhA0, 6), (WT5CCP0, 7)],
+        (Port::D, Seven) => &[(U2Tx, 1), (PhB0, 6), (WT5CCP1, 7), (NMI, 8)],
+
+        (Port::E, Zero) => &[(U7Rx, 1)],
+        (Port::E, One) => &[(U7Tx, 1)],
+        (Port::E, Two) | (Port::E, Three) | (Port::E, Six) | (Port::E, Seven) => &[],
+        (Port::E, Four) => &[
+            (U5Rx, 1),
+            (I2C2SCL, 3),
+            (M0PWM4, 4),
+            (M1PWM2, 5),
+            (CAN0Rx, 8),
+        ],
+        (Port::E, Five) => &[
+            (U5Tx, 1),
+            (I2C2SDA, 3),
+            (M0PWM5, 4),
+            (M1PWM3, 5),
+            (CAN0Tx, 8),
+        ],
+
+        (Port::F, Zero) => &[
+            (U1RTS, 1),
+            (SSI1Rx, 2),
+            (CAN0Rx, 3),
+            (M1PWM4, 5),
+            (PhA0, 6),
+            (T0CCP0, 7),
+            (NMI, 8),
+            (C0o, 9),
+        ],
+        (Port::F, One) => &[
+            (U1CTS, 1),
+            (SSI1Tx, 2),
+            (M1PWM5, 5),
+            (PhB0, 6),
+            (T0CCP1, 7),
+            (C1o, 9),
+            (TRD1, 14),
+        ],
+        (Port::F, Two) => &[
+            (SSI1Clk, 2),
+            (M0FAULT0, 4),
+            (M1PWM6, 5),
+            (T1CCP0, 7),
+            (TRD0, 14),
+        ],
+        (Port::F, Three) => &[
+            (SSI1Fss, 2),
+            (CAN0Tx, 3),
+            (M1PWM7, 5),
+            (T1CCP1, 7),
+            (TRCLK, 14),
+        ],
+        (Port::F, Four) => &[(M1FAULT0, 5), (IDX0, 6), (T2CCP0, 7), (USB0EPEN, 8)],
+        (Port::F, Five) | (Port::F, Six) | (Port::F, Seven) => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_same_signal_can_need_different_port_control_values() {
+        assert_eq!(
+            mux(Port::C, Pin::Four, Function::U4Rx),
+            Some(Mux::Alternate(Bits::new(1)))
+        );
+        assert_eq!(
+            mux(Port::C, Pin::Four, Function::U1Rx),
+            Some(Mux::Alternate(Bits::new(2)))
+        );
+        assert_eq!(
+            mux(Port::B, Pin::Zero, Function::U1Rx),
+            Some(Mux::Alternate(Bits::new(1)))
+        );
+        assert_eq!(
+            mux(Port::D, Pin::Six, Function::U2Rx),
+            Some(Mux::Alternate(Bits::new(1)))
+        );
+    }
+
+    #[test]
+    fn pins_only_have_the_functions_in_the_table() {
+        assert_eq!(mux(Port::A, Pin::Zero, Function::U1Rx), None);
+        assert_eq!(mux(Port::F, Pin::Zero, Function::Analog), None);
+        assert_eq!(
+            mux(Port::E, Pin::Three, Function::Analog),
+            Some(Mux::Analog)
+        );
+        assert_eq!(
+            mux(Port::F, Pin::Four, Function::Digital),
+            Some(Mux::Digital)
+        );
+        assert_eq!(mux(Port::F, Pin::Five, Function::Digital), None);
+    }
+}
@@ -0,0 +1,299 @@
+//! GPIO interrupts
+//!
+//! Each port has one interrupt in the NVIC that's shared by all of its pins. When it fires,
+//! the pins that triggered it are recorded as pending (for the program to poll with `take`)
+//! and the handler registered for each of them (if there is one) is called
+//!
+//! Page 657 of data sheet (Interrupt Control)
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::interrupts::{self, Mutex};
+use crate::Pin;
+
+use super::ports::{Bus, Port, PortRegisters};
+
+/// What makes a pin raise an interrupt
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    RisingEdge,
+    FallingEdge,
+    BothEdges,
+    /// Keeps raising the interrupt while the pin is high, so the pin's interrupt is disabled
+    /// once it's handled (and has to be enabled again to re-arm it)
+    HighLevel,
+    /// Keeps raising the interrupt while the pin is low, so the pin's interrupt is disabled
+    /// once it's handled (and has to be enabled again to re-arm it)
+    LowLevel,
+}
+
+const PORTS: usize = 6;
+const PINS: usize = 8;
+
+/// The pins of each port that have raised an interrupt that hasn't been taken yet (bit `n` for pin `n`)
+static PENDING: [AtomicU8; PORTS] = [const { AtomicU8::new(0) }; PORTS];
+
+/// The ports whose interrupts were enabled through the AHB aperture (bit `n` for port `n`),
+/// so the handler reaches them the same way without reading GPIOHBCTL
+static HIGH_PERFORMANCE_BUS: AtomicU8 = AtomicU8::new(0);
+
+type Handler = Mutex<Cell<Option<fn()>>>;
+
+/// The handler for each pin of each port
+static HANDLERS: [Handler; PORTS * PINS] = [const { Mutex::new(Cell::new(None)) }; PORTS * PINS];
+
+fn handler(port: Port, pin: Pin) -> &'static Handler {
+    &HANDLERS[port as usize * PINS + pin as usize]
+}
+
+fn mask(bits: &[u32]) -> u8 {
+    bits.iter().fold(0, |mask, bit| mask | 1 << bit)
+}
+
+/// Page 657 of data sheet (the interrupt is masked while its sense is being changed,
+/// so the change itself can't raise it)
+pub(super) fn enable(registers: PortRegisters, bits: &[u32], trigger: Trigger) {
+    let port = registers.port;
+    let mask = mask(bits);
+
+    match registers.bus {
+        Bus::AdvancedPeripheral => {
+            HIGH_PERFORMANCE_BUS.fetch_and(!(1 << port as u8), Ordering::Relaxed)
+        }
+        Bus::AdvancedHighPerformance => {
+            HIGH_PERFORMANCE_BUS.fetch_or(1 << port as u8, Ordering::Relaxed)
+        }
+    };
+
+    interrupts::free(|_| {
+        registers.interrupt_mask().clear_bits(bits);
+
+        // Level or edge
+        if let Trigger::HighLevel | Trigger::LowLevel = trigger {
+            registers.interrupt_sense().set_bits(bits);
+        } else {
+            registers.interrupt_sense().clear_bits(bits);
+        }
+
+        // Both edges, or just the one picked by the interrupt event register
+        if let Trigger::BothEdges = trigger {
+            registers.interrupt_both_edges().set_bits(bits);
+        } else {
+            registers.interrupt_both_edges().clear_bits(bits);
+        }
+
+        // Rising edge or high level (otherwise falling edge or low level)
+        if let Trigger::RisingEdge | Trigger::HighLevel = trigger {
+            registers.interrupt_event().set_bits(bits);
+        } else {
+            registers.interrupt_event().clear_bits(bits);
+        }
+
+        // Forget anything raised before now
+        registers.interrupt_clear().write(mask as u32);
+        PENDING[port as usize].fetch_and(!mask, Ordering::Relaxed);
+
+        registers.interrupt_mask().set_bits(bits);
+    });
+
+    interrupts::enable(port.interrupt());
+}
+
+/// Stop the pins from raising interrupts
+///
+/// The port's interrupt stays enabled in the NVIC, since other pins of the port may still be using it
+pub(super) fn disable(registers: PortRegisters, bits: &[u32]) {
+    let port = registers.port;
+    let mask = mask(bits);
+
+    interrupts::free(|_| {
+        registers.interrupt_mask().clear_bits(bits);
+        PENDING[port as usize].fetch_and(!mask, Ordering::Relaxed);
+    });
+}
+
+/// Whether each pin has raised an interrupt since it was last taken, forgetting it if it has
+pub(super) fn take<const N: usize>(port: Port, bits: &[u32; N]) -> [bool; N] {
+    let pending = PENDING[port as usize].fetch_and(!mask(bits), Ordering::Relaxed);
+
+    bits.map(|bit| pending & 1 << bit != 0)
+}
+
+pub(super) fn set_handler(port: Port, pin: Pin, new_handler: Option<fn()>) {
+    interrupts::free(|critical_section| {
+        handler(port, pin).borrow(critical_section).set(new_handler)
+    });
+}
+
+/// Record which pins raised the port's interrupt and call their handlers
+pub(crate) fn handle(port: Port) {
+    let registers = if HIGH_PERFORMANCE_BUS.load(Ordering::Relaxed) & 1 << port as u8 != 0 {
+        port.registers(Bus::AdvancedHighPerformance)
+    } else {
+        port.registers(Bus::AdvancedPeripheral)
+    };
+
+    let triggered = registers.masked_interrupt_status().read() & 0xFF;
+
+    // A level keeps raising the interrupt for as long as it's held,
+    // so it has to be masked or the interrupt would never stop firing
+    let levels = triggered & registers.interrupt_sense().read();
+    if levels != 0 {
+        registers
+            .interrupt_mask()
+            .update_critical(&|current| current & !levels);
+    }
+
+    registers.interrupt_clear().write(triggered);
+    PENDING[port as usize].fetch_or(triggered as u8, Ordering::Relaxed);
+
+    for pin in [
+        Pin::Zero,
+        Pin::One,
+        Pin::Two,
+        Pin::Three,
+        Pin::Four,
+        Pin::Five,
+        Pin::Six,
+        Pin::Seven,
+    ] {
+        if triggered & 1 << pin as u32 == 0 {
+            continue;
+        }
+
+        // Copied out so the handler itself runs with interrupts enabled
+        if let Some(handler) =
+            interrupts::free(|critical_section| handler(port, pin).borrow(critical_section).get())
+        {
+            handler();
+        }
+    }
+}
+
+/// The port interrupt handlers named in the vector table
+#[cfg(not(test))]
+mod vectors {
+    use super::{handle, Port};
+
+    #[no_mangle]
+    extern "C" fn GPIOA() {
+        handle(Port::A);
+    }
+    #[no_mangle]
+    extern "C" fn GPIOB() {
+        handle(Port::B);
+    }
+    #[no_mangle]
+    extern "C" fn GPIOC() {
+        handle(Port::C);
+    }
+    #[no_mangle]
+    extern "C" fn GPIOD() {
+        handle(Port::D);
+    }
+    #[no_mangle]
+    extern "C" fn GPIOE() {
+        handle(Port::E);
+    }
+    #[no_mangle]
+    extern "C" fn GPIOF() {
+        handle(Port::F);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::simulated;
+    use std::sync::atomic::AtomicU32;
+
+    #[test]
+    fn enabling_configures_the_sense_then_unmasks() {
+        simulated::with(|memory| {
+            memory.preset(Port::C.apb().interrupt_event().address(), 0b1111_0000);
+        });
+
+        enable(Port::C.apb(), &[4, 5], Trigger::FallingEdge);
+
+        simulated::with(|memory| {
+            assert_eq!(memory.value(Port::C.apb().interrupt_sense().address()), 0);
+            assert_eq!(
+                memory.value(Port::C.apb().interrupt_both_edges().address()),
+                0
+            );
+            assert_eq!(
+                memory.value(Port::C.apb().interrupt_event().address()),
+                0b1100_0000
+            );
+            assert_eq!(
+                memory.value(Port::C.apb().interrupt_mask().address()),
+                0b11_0000
+            );
+            assert!(memory
+                .writes()
+                .contains(&(Port::C.apb().interrupt_clear().address(), 0b11_0000)));
+
+            // The port's interrupt is only enabled in the NVIC once the pins are ready
+            assert_eq!(
+                memory.writes().last(),
+                Some(&(0xE000_E100 as *mut u32, 1 << 2))
+            );
+        });
+    }
+
+    static CALLS: AtomicU32 = AtomicU32::new(0);
+
+    #[test]
+    fn handling_records_calls_and_masks_levels() {
+        set_handler(
+            Port::D,
+            Pin::Six,
+            Some(|| {
+                CALLS.fetch_add(1, Ordering::Relaxed);
+            }),
+        );
+        enable(Port::D.apb(), &[6], Trigger::LowLevel);
+        enable(Port::D.apb(), &[1], Trigger::RisingEdge);
+
+        simulated::with(|memory| {
+            memory.preset(
+                Port::D.apb().masked_interrupt_status().address(),
+                0b0100_0010,
+            );
+            memory.clear_accesses();
+        });
+
+        handle(Port::D);
+
+        simulated::with(|memory| {
+            assert_eq!(
+                memory.value(Port::D.apb().interrupt_mask().address()),
+                0b10,
+                "only the level-triggered pin should be masked"
+            );
+            assert!(memory
+                .writes()
+                .contains(&(Port::D.apb().interrupt_clear().address(), 0b0100_0010)));
+        });
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+
+        assert_eq!(take(Port::D, &[1, 6, 7]), [true, true, false]);
+        assert_eq!(take(Port::D, &[1, 6, 7]), [false, false, false]);
+    }
+
+    #[test]
+    fn handling_goes_through_the_bus_the_interrupts_were_enabled_on() {
+        let registers = Port::A.registers(Bus::AdvancedHighPerformance);
+        enable(registers, &[3], Trigger::RisingEdge);
+
+        simulated::with(|memory| {
+            memory.preset(registers.masked_interrupt_status().address(), 0b1000);
+            memory.preset(Port::A.apb().masked_interrupt_status().address(), 0b0001);
+        });
+
+        handle(Port::A);
+
+        assert_eq!(take(Port::A, &[0, 3]), [false, true]);
+    }
+}
@@ -1,2 +1,5 @@
+pub mod bus;
+pub mod functions;
+pub mod interrupts;
 pub mod pins;
 pub mod ports;
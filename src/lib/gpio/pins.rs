@@ -1,12 +1,14 @@
-use crate::{
-    memory,
-    utils::{pins_to_bits, reverse_array},
-    H, L,
-};
+use crate::interrupts;
+use crate::registers::gpio::{DATA, PCTL};
+use crate::registers::{Bits, Register};
+use crate::utils::pins_to_bits;
 
-use super::ports::Port;
+use super::functions::{mux, Function, Mux};
+use super::interrupts as gpio_interrupts;
+pub use super::interrupts::Trigger;
+use super::ports::{check_pins, PinSetupError, Port, PortRegisters};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Pin {
     Zero = 0,
     One = 1,
@@ -17,15 +19,6 @@ pub enum Pin {
     Six = 6,
     Seven = 7,
 }
-pub enum Function {
-    Analog,
-    Digital,
-    CAN,
-    I2C,
-    PWM,
-    UART,
-}
-
 pub enum Pull {
     Down,
     Up,
@@ -38,237 +31,877 @@ pub struct ReadablePinOptions {
     pub pull: Pull,
 }
 pub struct ReadablePins<const N: usize> {
-    data_address: *mut u32,
+    data: Register<DATA>,
+    registers: PortRegisters,
     pins: [ReadablePin; N],
 }
 impl<const N: usize> ReadablePins<N> {
-    pub fn pins(&self) -> [ReadablePin; N] {
+    pub fn pins(&self) -> &[ReadablePin; N] {
+        &self.pins
+    }
+
+    /// Split into the individual pins
+    pub fn into_pins(self) -> [ReadablePin; N] {
         self.pins
     }
 
     pub fn read_all(&self) -> [bool; N] {
-        unsafe { memory::read_bits(self.data_address, &self.pins.map(|pin| pin.pin as u32)) }
+        self.data
+            .read_bits(&self.pins.each_ref().map(|pin| pin.pin as u32))
+    }
+
+    /// Raise an interrupt whenever any of the pins see `trigger`
+    ///
+    /// Page 657 of data sheet
+    pub fn enable_interrupts(&mut self, trigger: Trigger) {
+        gpio_interrupts::enable(self.registers, &self.bits(), trigger);
+    }
+    pub fn disable_interrupts(&mut self) {
+        gpio_interrupts::disable(self.registers, &self.bits());
+    }
+    /// Whether each pin has raised an interrupt since this was last called
+    pub fn take_interrupts(&self) -> [bool; N] {
+        gpio_interrupts::take(self.registers.port, &self.bits())
+    }
+
+    fn bits(&self) -> [u32; N] {
+        self.pins.each_ref().map(|pin| pin.pin as u32)
     }
 }
-#[derive(Clone, Copy)]
 pub struct ReadablePin {
-    data_address: *mut u32,
-    pin: Pin,
+    data: Register<DATA>,
+    pub(super) registers: PortRegisters,
+    pub(super) pin: Pin,
 }
 impl ReadablePin {
+    pub fn pin(&self) -> Pin {
+        self.pin
+    }
+
     pub fn read(&self) -> bool {
-        let current = unsafe { memory::read(self.data_address) };
-        current & (1 << self.pin as u32) != 0
+        self.data.read_bit_atomic(self.pin as u32)
+    }
+
+    /// Raise an interrupt whenever the pin sees `trigger`
+    ///
+    /// Page 657 of data sheet
+    pub fn enable_interrupt(&mut self, trigger: Trigger) {
+        gpio_interrupts::enable(self.registers, &[self.pin as u32], trigger);
+    }
+    pub fn disable_interrupt(&mut self) {
+        gpio_interrupts::disable(self.registers, &[self.pin as u32]);
+    }
+    /// Whether the pin has raised an interrupt since this was last called
+    pub fn take_interrupt(&self) -> bool {
+        let [pending] = gpio_interrupts::take(self.registers.port, &[self.pin as u32]);
+        pending
+    }
+
+    /// Call `handler` (from the port's interrupt handler) whenever the pin raises an interrupt
+    pub fn set_interrupt_handler(&mut self, handler: fn()) {
+        gpio_interrupts::set_handler(self.registers.port, self.pin, Some(handler));
+    }
+    pub fn clear_interrupt_handler(&mut self) {
+        gpio_interrupts::set_handler(self.registers.port, self.pin, None);
+    }
+
+    /// Start driving the pin instead (its pull resistor is kept, for open drain lines)
+    ///
+    /// Its interrupt is disabled and its handler is cleared
+    pub fn into_writable(self) -> WritablePin {
+        self.into_flex().into_writable()
     }
+    pub fn into_flex(self) -> FlexPin {
+        gpio_interrupts::disable(self.registers, &[self.pin as u32]);
+        gpio_interrupts::set_handler(self.registers.port, self.pin, None);
+
+        FlexPin {
+            data: self.data,
+            registers: self.registers,
+            pin: self.pin,
+            output: false,
+        }
+    }
+}
+
+/// How strongly a writable pin is driven
+///
+/// Page 673 of data sheet (and pages 674 and 675 for 4 and 8 mA)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Drive {
+    TwoMilliamps,
+    FourMilliamps,
+    /// Slew rate control (page 681 of data sheet) makes the edges slower, which cuts down on noise.
+    /// It's only available at 8 mA
+    EightMilliamps {
+        slew_rate_control: bool,
+    },
 }
 
 pub struct WritablePinOptions {
     pub function: Function,
+    pub drive: Drive,
+    /// Only pull the pin low, leaving it floating when it's set (page 676 of data sheet)
+    ///
+    /// I2C data pins are always open drain, whatever this is set to
+    pub open_drain: bool,
+}
+
+impl Default for WritablePinOptions {
+    /// A push-pull digital output driven at 2 mA, which is how the pins come out of reset
+    fn default() -> Self {
+        WritablePinOptions {
+            function: Function::Digital,
+            drive: Drive::TwoMilliamps,
+            open_drain: false,
+        }
+    }
 }
 pub struct WritablePins<const N: usize> {
-    data_address: *mut u32,
-    pins: [WritablePin; N],
+    pub(super) data: Register<DATA>,
+    pub(super) pins: [WritablePin; N],
 }
 impl<const N: usize> WritablePins<N> {
-    pub fn pins(&self) -> [WritablePin; N] {
+    pub fn pins(&self) -> &[WritablePin; N] {
+        &self.pins
+    }
+
+    /// Split into the individual pins
+    pub fn into_pins(self) -> [WritablePin; N] {
         self.pins
     }
 
     pub fn read_all(&self) -> [bool; N] {
-        unsafe { memory::read_bits(self.data_address, &self.pins.map(|pin| pin.pin as u32)) }
+        self.data
+            .read_bits(&self.pins.each_ref().map(|pin| pin.pin as u32))
     }
     pub fn write_all(&mut self, values: [bool; N]) {
-        unsafe {
-            memory::write_bits(
-                self.data_address,
-                &self.pins.map(|pin| pin.pin as u32),
-                values,
-            )
-        }
+        interrupts::free(|_| {
+            self.data
+                .write_bits(&self.pins.each_ref().map(|pin| pin.pin as u32), values)
+        });
     }
     pub fn update_all(&mut self, updater: &dyn Fn([bool; N]) -> [bool; N]) {
         self.write_all(updater(self.read_all()));
     }
 
     pub fn clear_all(&mut self) {
-        unsafe {
-            memory::clear_bits(self.data_address, &self.pins.map(|pin| pin.pin as u32));
-        }
+        interrupts::free(|_| {
+            self.data
+                .clear_bits(&self.pins.each_ref().map(|pin| pin.pin as u32))
+        });
     }
     pub fn set_all(&mut self) {
-        unsafe {
-            memory::set_bits(self.data_address, &self.pins.map(|pin| pin.pin as u32));
-        }
+        interrupts::free(|_| {
+            self.data
+                .set_bits(&self.pins.each_ref().map(|pin| pin.pin as u32))
+        });
     }
     pub fn toggle_all(&mut self) {
-        unsafe {
-            memory::toggle_bits(self.data_address, &self.pins.map(|pin| pin.pin as u32));
-        }
+        interrupts::free(|_| {
+            self.data
+                .toggle_bits(&self.pins.each_ref().map(|pin| pin.pin as u32))
+        });
     }
 }
 
-#[derive(Clone, Copy)]
 pub struct WritablePin {
-    data_address: *mut u32,
-    pin: Pin,
+    data: Register<DATA>,
+    pub(super) registers: PortRegisters,
+    pub(super) pin: Pin,
 }
 impl WritablePin {
+    pub fn pin(&self) -> Pin {
+        self.pin
+    }
+
     pub fn read(&self) -> bool {
-        let current = unsafe { memory::read(self.data_address) };
-        current & (1 << self.pin as u32) != 0
+        self.data.read_bit_atomic(self.pin as u32)
     }
     pub fn clear(&mut self) {
-        unsafe {
-            memory::clear_bits(self.data_address, &[self.pin as u32]);
-        }
+        self.data.clear_bit_atomic(self.pin as u32);
     }
     pub fn set(&mut self) {
-        unsafe {
-            memory::set_bits(self.data_address, &[self.pin as u32]);
-        }
+        self.data.set_bit_atomic(self.pin as u32);
     }
     pub fn toggle(&mut self) {
-        unsafe {
-            memory::toggle_bits(self.data_address, &[self.pin as u32]);
+        let bit = 1 << self.pin as u32;
+        self.data.update_critical(&|current| current ^ bit);
+    }
+
+    /// Stop driving the pin and listen to it instead
+    pub fn into_readable(self, pull: Pull) -> ReadablePin {
+        self.into_flex().into_readable(pull)
+    }
+    pub fn into_flex(self) -> FlexPin {
+        FlexPin {
+            data: self.data,
+            registers: self.registers,
+            pin: self.pin,
+            output: true,
         }
     }
 }
 
-/// Page 684 of the data sheet for how the lock mechanism works
-const UNLOCK: u32 = 0x4C4F434B;
+/// A pin that can switch between driving and listening as often as it needs to
+/// (for protocols like 1-Wire that share one line for both)
+///
+/// Only its direction (and pull resistor) changes, the rest of its setup stays as it was
+pub struct FlexPin {
+    data: Register<DATA>,
+    pub(super) registers: PortRegisters,
+    pub(super) pin: Pin,
+    output: bool,
+}
+impl FlexPin {
+    pub fn pin(&self) -> Pin {
+        self.pin
+    }
+    pub fn is_output(&self) -> bool {
+        self.output
+    }
 
-fn setup_pins<const N: usize>(
-    port: Port,
-    pins: [Pin; N],
-    writable: bool,
-    function: Function,
-    pull: Pull,
-) {
-    // Unlock the pins
-    unsafe {
-        memory::write(port.lock(), UNLOCK);
+    /// Page 663 of data sheet
+    pub fn set_as_output(&mut self) {
+        self.registers
+            .direction()
+            .update_critical(&|current| current | 1 << self.pin as u32);
+        self.output = true;
     }
+    /// Page 663 of data sheet
+    pub fn set_as_input(&mut self, pull: Pull) {
+        let bits = [self.pin as u32];
 
-    // Set to output pins if output (otherwise set to input)
-    if writable {
-        unsafe {
-            memory::set_bits(port.direction(), &pins_to_bits(&pins));
-        }
-    } else {
-        unsafe {
-            memory::clear_bits(port.direction(), &pins_to_bits(&pins));
-        }
+        // The NMI pins ignore pull resistor changes unless they're committed first
+        let protected = (1 << self.pin as u32) & self.registers.port.protected_pins() as u32;
+
+        interrupts::free(|_| {
+            self.registers.direction().clear_bits(&bits);
+
+            unlock(self.registers, protected);
+            set_pull(self.registers, &bits, pull);
+            relock(self.registers, protected);
+        });
+        self.output = false;
+    }
+
+    /// Reads the level on the pin whichever way it's going
+    pub fn read(&self) -> bool {
+        self.data.read_bit_atomic(self.pin as u32)
+    }
+    /// Only changes the level on the pin while it's an output
+    pub fn clear(&mut self) {
+        self.data.clear_bit_atomic(self.pin as u32);
+    }
+    /// Only changes the level on the pin while it's an output
+    pub fn set(&mut self) {
+        self.data.set_bit_atomic(self.pin as u32);
     }
 
-    // Disable alternate function when it's not used (and enable it when it is)
-    if let Function::Analog | Function::Digital = function {
-        unsafe {
-            memory::clear_bits(port.alternate_function_select(), &pins_to_bits(&pins));
+    pub fn into_readable(mut self, pull: Pull) -> ReadablePin {
+        self.set_as_input(pull);
+
+        ReadablePin {
+            data: self.data,
+            registers: self.registers,
+            pin: self.pin,
         }
-    } else {
-        unsafe {
-            memory::set_bits(port.alternate_function_select(), &pins_to_bits(&pins));
+    }
+    pub fn into_writable(mut self) -> WritablePin {
+        if !self.output {
+            self.set_as_output();
+        }
+
+        WritablePin {
+            data: self.data,
+            registers: self.registers,
+            pin: self.pin,
         }
     }
+}
 
-    // Configure pull-up and pull-down resistors
+/// Configure pull-up and pull-down resistors
+///
+/// Setting a pin's bit in one of them clears it in the other (pages 677 and 679 of data sheet)
+fn set_pull(registers: PortRegisters, bits: &[u32], pull: Pull) {
     match pull {
-        Pull::Down => unsafe {
-            memory::set_bits(port.pull_down_select(), &pins_to_bits(&pins));
-        },
-        Pull::Up => unsafe {
-            memory::set_bits(port.pull_up_select(), &pins_to_bits(&pins));
-        },
+        Pull::Down => registers.pull_down_select().set_bits(bits),
+        Pull::Up => registers.pull_up_select().set_bits(bits),
         Pull::Neither => {
-            unsafe {
-                memory::clear_bits(port.pull_up_select(), &pins_to_bits(&pins));
-            }
-            unsafe {
-                memory::clear_bits(port.pull_down_select(), &pins_to_bits(&pins));
-            }
+            registers.pull_up_select().clear_bits(bits);
+            registers.pull_down_select().clear_bits(bits);
         }
     }
+}
+
+/// Page 684 of the data sheet for how the lock mechanism works
+const UNLOCK: u32 = 0x4C4F434B;
 
-    unsafe {
-        memory::set_bits(port.commit(), &pins_to_bits(&pins));
+/// Unlock the port and commit the `protected` pins (bit `n` for pin `n`) so their AFSEL, PUR, PDR, and DEN bits can change
+/// (nothing is unlocked if there aren't any)
+///
+/// Has to be done without interrupts, so a handler can't reconfigure the port before it's relocked
+///
+/// Page 685 of data sheet (GPIOCR)
+fn unlock(registers: PortRegisters, protected: u32) {
+    if protected != 0 {
+        registers.lock().write(UNLOCK);
+        registers.commit().update(&|current| current | protected);
     }
+}
 
-    // Enable digital function when it's needed (and disable it when it's not)
-    match function {
-        Function::Digital | Function::UART => unsafe {
-            memory::set_bits(port.digital_enable(), &pins_to_bits(&pins));
-        },
-        Function::Analog => unsafe {
-            memory::clear_bits(port.digital_enable(), &pins_to_bits(&pins));
-        },
-        _ => todo!(), // and rewrite to if let when solved
+/// Protect the pins [`unlock`] committed again
+fn relock(registers: PortRegisters, protected: u32) {
+    if protected != 0 {
+        registers.commit().update(&|current| current & !protected);
+        registers.lock().write(0);
     }
+}
 
-    // Enable analog when it's needed (and disable it when it's not)
-    if let Function::Analog = function {
-        unsafe {
-            memory::set_bits(port.analog_mode_select(), &pins_to_bits(&pins));
+/// `unlock_nmi_pins` is whether the port was set up with [`PortOptions::unlock_nmi_pins`](super::ports::PortOptions::unlock_nmi_pins),
+/// which is the only way the NMI pins get unlocked
+#[allow(clippy::too_many_arguments)]
+fn setup_pins<const N: usize>(
+    registers: PortRegisters,
+    unlock_nmi_pins: bool,
+    pins: [Pin; N],
+    writable: bool,
+    function: Function,
+    pull: Pull,
+    drive: Drive,
+    open_drain: bool,
+) {
+    let port = registers.port;
+    let bits = pins_to_bits(&pins);
+    let protected = bits.iter().fold(0, |mask, bit| mask | 1 << bit) & port.protected_pins() as u32;
+
+    assert!(
+        bits.iter().all(|bit| port.debug_pins() & 1 << bit == 0),
+        "the JTAG and SWD pins (PC0 to PC3) can't be repurposed"
+    );
+    assert!(
+        unlock_nmi_pins || protected == 0,
+        "the NMI pins (PD7 and PF0) can only be set up on a port set up to unlock them"
+    );
+
+    // The whole unlock-configure-lock sequence (and every read-modify-write in it)
+    // has to happen without an interrupt handler reconfiguring the same port in the middle
+    interrupts::free(|_| {
+        // Only protected pins need to be unlocked and committed for their AFSEL, PUR, PDR, and DEN bits to change
+        unlock(registers, protected);
+
+        // Set to output pins if output (otherwise set to input)
+        if writable {
+            registers.direction().set_bits(&bits);
+        } else {
+            registers.direction().clear_bits(&bits);
         }
-    } else {
-        unsafe {
-            memory::clear_bits(port.analog_mode_select(), &pins_to_bits(&pins));
+
+        // Disable alternate function when it's not used (and enable it when it is)
+        if let Function::Analog | Function::Digital = function {
+            registers.alternate_function_select().clear_bits(&bits);
+        } else {
+            registers.alternate_function_select().set_bits(&bits);
         }
-    }
 
-    // Table 10-2 on page 650-651 of data sheet
-    let digital_function = match function {
-        Function::Analog => None,
-        Function::Digital => Some([L, L, L, L]),
-        Function::CAN => Some([H, L, L, L]),
-        Function::I2C => Some([L, L, H, H]),
-        Function::PWM => Some([L, H, L, H]),
-        Function::UART => Some([L, L, L, H]),
-    };
-    if let Some(array) = digital_function {
-        let port_control_values = reverse_array(array);
+        set_pull(registers, &bits, pull);
 
-        for pin in pins {
-            let mut memory_bits = [0; 4];
+        // Enable digital function when it's needed (and disable it when it's not)
+        if let Function::Analog = function {
+            registers.digital_enable().clear_bits(&bits);
+        } else {
+            registers.digital_enable().set_bits(&bits);
+        }
 
-            let min = (pin as u32) * 4;
-            let max = min + 3;
-            let range = min..=max;
+        // Setting a pin's bit in one drive select register clears it in the other two
+        match drive {
+            Drive::TwoMilliamps => registers.two_milliamp_drive_select().set_bits(&bits),
+            Drive::FourMilliamps => registers.four_milliamp_drive_select().set_bits(&bits),
+            Drive::EightMilliamps { .. } => registers.eight_milliamp_drive_select().set_bits(&bits),
+        }
+        if let Drive::EightMilliamps {
+            slew_rate_control: true,
+        } = drive
+        {
+            registers.slew_rate_control_select().set_bits(&bits);
+        } else {
+            registers.slew_rate_control_select().clear_bits(&bits);
+        }
 
-            for (i, memory_bit) in range.enumerate() {
-                memory_bits[i] = memory_bit;
-            }
+        // Make the pins open drain when asked to or the function needs it (and push-pull otherwise)
+        if open_drain || function.open_drain() {
+            registers.open_drain_select().set_bits(&bits);
+        } else {
+            registers.open_drain_select().clear_bits(&bits);
+        }
 
-            unsafe {
-                memory::write_bits(port.port_control(), &memory_bits, port_control_values);
-            }
+        // Enable analog when it's needed (and disable it when it's not)
+        if let Function::Analog = function {
+            registers.analog_mode_select().set_bits(&bits);
+        } else {
+            registers.analog_mode_select().clear_bits(&bits);
         }
-    }
-    
-    unsafe {
-        memory::write(port.lock(), 0);
-    }
+
+        // Table 23-5 on page 1351 of data sheet (the same signal can need a different value on different pins)
+        for pin in pins {
+            let port_control_value = match mux(port, pin, function) {
+                Some(Mux::Alternate(port_control_value)) => port_control_value,
+                Some(Mux::Digital) => Bits::new(0x0),
+                Some(Mux::Analog) => continue,
+                None => unreachable!("the pins are checked before they're set up"),
+            };
+
+            registers
+                .port_control()
+                .modify(PCTL::pmc(pin), port_control_value);
+        }
+
+        // Protect them again
+        relock(registers, protected);
+    });
 }
 
-pub fn setup_readable_pins<const N: usize>(
+/// Set up pins without claiming them from the port's [`UsablePort`](super::ports::UsablePort),
+/// whether or not they're already set up (which bus the port is on is read from GPIOHBCTL)
+///
+/// Only for the fault handlers, which take the board over from whatever was using it, and for tests.
+/// Fails if any of them can't be used for the function, or are protected (see [`PinSetupError`])
+pub(crate) fn steal_readable_pins<const N: usize>(
     port: Port,
     pins: [Pin; N],
     options: ReadablePinOptions,
-) -> ReadablePins<N> {
-    setup_pins(port, pins, false, options.function, options.pull);
+) -> Result<ReadablePins<N>, PinSetupError> {
+    check_pins(port, false, &pins, options.function)?;
 
-    let data_address = port.data(&pins);
-    let pins: [ReadablePin; N] = pins.map(|pin| ReadablePin { data_address, pin });
-    ReadablePins { data_address, pins }
+    Ok(setup_readable_pins_on(
+        port.registers(port.bus()),
+        false,
+        pins,
+        options,
+    ))
 }
 
-pub fn setup_writable_pins<const N: usize>(
+/// Like [`steal_readable_pins`], but for pins to be written to
+pub(crate) fn steal_writable_pins<const N: usize>(
     port: Port,
     pins: [Pin; N],
     options: WritablePinOptions,
+) -> Result<WritablePins<N>, PinSetupError> {
+    check_pins(port, false, &pins, options.function)?;
+
+    Ok(setup_writable_pins_on(
+        port.registers(port.bus()),
+        false,
+        pins,
+        options,
+    ))
+}
+
+pub(super) fn setup_readable_pins_on<const N: usize>(
+    registers: PortRegisters,
+    unlock_nmi_pins: bool,
+    pins: [Pin; N],
+    options: ReadablePinOptions,
+) -> ReadablePins<N> {
+    setup_pins(
+        registers,
+        unlock_nmi_pins,
+        pins,
+        false,
+        options.function,
+        options.pull,
+        Drive::TwoMilliamps,
+        false,
+    );
+
+    let data = registers.data(&pins);
+    let pins: [ReadablePin; N] = pins.map(|pin| ReadablePin {
+        data,
+        registers,
+        pin,
+    });
+    ReadablePins {
+        data,
+        registers,
+        pins,
+    }
+}
+
+pub(super) fn setup_writable_pins_on<const N: usize>(
+    registers: PortRegisters,
+    unlock_nmi_pins: bool,
+    pins: [Pin; N],
+    options: WritablePinOptions,
 ) -> WritablePins<N> {
-    setup_pins(port, pins, true, options.function, Pull::Neither);
+    setup_pins(
+        registers,
+        unlock_nmi_pins,
+        pins,
+        true,
+        options.function,
+        Pull::Neither,
+        options.drive,
+        options.open_drain,
+    );
+
+    let data = registers.data(&pins);
+    let pins: [WritablePin; N] = pins.map(|pin| WritablePin {
+        data,
+        registers,
+        pin,
+    });
+    WritablePins { data, pins }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpio::ports::{setup_port, PortOptions, UsablePort};
+    use crate::memory::simulated;
+    use crate::{Board, H, L};
+
+    /// Port F set up to let PF0 (the NMI pin) be used, with its setup's accesses forgotten
+    fn port_f_with_the_nmi_pin_unlocked() -> UsablePort {
+        let port = setup_port(
+            Board,
+            Port::F,
+            PortOptions {
+                unlock_nmi_pins: true,
+                ..Default::default()
+            },
+        );
+        simulated::with(|memory| memory.clear_accesses());
+        port
+    }
+
+    #[test]
+    fn only_protected_pins_are_unlocked_and_committed() {
+        let mut port = port_f_with_the_nmi_pin_unlocked();
+        port.setup_writable_pins(
+            [Pin::Zero, Pin::One],
+            WritablePinOptions {
+                function: Function::Digital,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        simulated::with(|memory| {
+            let writes = memory.writes();
+            assert_eq!(
+                writes.first(),
+                Some(&(Port::F.apb().lock().address(), UNLOCK))
+            );
+            assert_eq!(
+                writes.get(1),
+                Some(&(Port::F.apb().commit().address(), 0b01))
+            );
+            assert_eq!(writes.last(), Some(&(Port::F.apb().lock().address(), 0)));
+            assert_eq!(memory.value(Port::F.apb().commit().address()), 0);
+            assert_eq!(memory.value(Port::F.apb().direction().address()), 0b11);
+            assert_eq!(memory.value(Port::F.apb().digital_enable().address()), 0b11);
+            memory.clear_accesses();
+        });
 
-    let data_address = port.data(&pins);
-    let pins: [WritablePin; N] = pins.map(|pin| WritablePin { data_address, pin });
-    WritablePins { data_address, pins }
+        port.setup_writable_pins([Pin::Three], WritablePinOptions::default())
+            .unwrap();
+
+        simulated::with(|memory| {
+            assert!(!memory
+                .writes()
+                .iter()
+                .any(|(address, _)| *address == Port::F.apb().lock().address()
+                    || *address == Port::F.apb().commit().address()));
+            assert_eq!(memory.value(Port::F.apb().direction().address()), 0b1011);
+        });
+    }
+
+    #[test]
+    fn the_nmi_pins_are_only_unlocked_on_a_port_that_opted_in() {
+        const SWITCH: ReadablePinOptions = ReadablePinOptions {
+            function: Function::Digital,
+            pull: Pull::Up,
+        };
+
+        assert_eq!(
+            steal_readable_pins(Port::F, [Pin::Zero], SWITCH).err(),
+            Some(PinSetupError::Locked(Pin::Zero))
+        );
+
+        let mut port = setup_port(Board, Port::F, PortOptions::default());
+        assert_eq!(
+            port.setup_readable_pins([Pin::Zero], SWITCH).err(),
+            Some(PinSetupError::Locked(Pin::Zero))
+        );
+
+        simulated::with(|memory| {
+            assert!(!memory
+                .written_registers()
+                .iter()
+                .any(|address| *address == Port::F.apb().lock().address()
+                    || *address == Port::F.apb().commit().address()));
+        });
+    }
+
+    #[test]
+    fn stolen_pins_are_still_checked() {
+        assert_eq!(
+            steal_writable_pins(Port::C, [Pin::Two], WritablePinOptions::default()).err(),
+            Some(PinSetupError::Debug(Pin::Two))
+        );
+        assert_eq!(
+            steal_writable_pins(
+                Port::F,
+                [Pin::Three],
+                WritablePinOptions {
+                    function: Function::U0Tx,
+                    ..Default::default()
+                }
+            )
+            .err(),
+            Some(PinSetupError::Unavailable(Pin::Three, Function::U0Tx))
+        );
+    }
+
+    #[test]
+    fn writable_pins_use_the_masked_data_address() {
+        let mut pins = steal_writable_pins(
+            Port::F,
+            [Pin::One, Pin::Three],
+            WritablePinOptions {
+                function: Function::Digital,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        simulated::with(|memory| memory.clear_accesses());
+
+        pins.write_all([H, L]);
+
+        let data = Port::F.apb().data(&[Pin::One, Pin::Three]).address();
+        assert_eq!(data as u32, 0x4002_5000 + 0x28);
+        simulated::with(|memory| {
+            assert_eq!(memory.writes(), [(data, 0b0010)]);
+        });
+    }
+
+    #[test]
+    fn uart_pins_get_their_own_port_control_nibbles() {
+        steal_writable_pins(
+            Port::A,
+            [Pin::One],
+            WritablePinOptions {
+                function: Function::U0Tx,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        simulated::with(|memory| {
+            assert_eq!(memory.value(Port::A.apb().port_control().address()), 0x10);
+            assert_eq!(
+                memory.value(Port::A.apb().alternate_function_select().address()),
+                0b10
+            );
+            assert_ne!(
+                Port::A.apb().port_control().address(),
+                Port::A.apb().analog_mode_select().address()
+            );
+        });
+    }
+
+    #[test]
+    fn only_i2c_data_pins_are_open_drain() {
+        steal_writable_pins(
+            Port::B,
+            [Pin::Two],
+            WritablePinOptions {
+                function: Function::I2C0SCL,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        steal_writable_pins(
+            Port::B,
+            [Pin::Three],
+            WritablePinOptions {
+                function: Function::I2C0SDA,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        simulated::with(|memory| {
+            assert_eq!(
+                memory.value(Port::B.apb().open_drain_select().address()),
+                0b1000
+            );
+            assert_eq!(
+                memory.value(Port::B.apb().digital_enable().address()),
+                0b1100
+            );
+            assert_eq!(memory.value(Port::B.apb().port_control().address()), 0x3300);
+        });
+    }
+
+    #[test]
+    fn each_pin_takes_only_its_own_interrupt() {
+        let [mut low, high] = steal_readable_pins(
+            Port::E,
+            [Pin::Two, Pin::Three],
+            ReadablePinOptions {
+                function: Function::Digital,
+                pull: Pull::Up,
+            },
+        )
+        .unwrap()
+        .into_pins();
+
+        low.enable_interrupt(Trigger::BothEdges);
+        simulated::with(|memory| {
+            assert_eq!(
+                memory.value(Port::E.apb().interrupt_both_edges().address()),
+                0b0100
+            );
+            memory.preset(Port::E.apb().masked_interrupt_status().address(), 0b1100);
+        });
+        gpio_interrupts::handle(Port::E);
+
+        assert!(high.take_interrupt());
+        assert!(low.take_interrupt());
+        assert!(!low.take_interrupt());
+    }
+
+    #[test]
+    fn pad_options_select_drive_slew_rate_and_open_drain() {
+        steal_writable_pins(
+            Port::C,
+            [Pin::Four],
+            WritablePinOptions {
+                drive: Drive::EightMilliamps {
+                    slew_rate_control: true,
+                },
+                open_drain: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        steal_writable_pins(
+            Port::C,
+            [Pin::Five],
+            WritablePinOptions {
+                drive: Drive::FourMilliamps,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        simulated::with(|memory| {
+            assert_eq!(
+                memory.value(Port::C.apb().eight_milliamp_drive_select().address()),
+                0b01_0000
+            );
+            assert_eq!(
+                memory.value(Port::C.apb().four_milliamp_drive_select().address()),
+                0b10_0000
+            );
+            assert_eq!(
+                memory.value(Port::C.apb().slew_rate_control_select().address()),
+                0b01_0000
+            );
+            assert_eq!(
+                memory.value(Port::C.apb().open_drain_select().address()),
+                0b01_0000
+            );
+        });
+    }
+
+    #[test]
+    fn flex_pins_only_flip_direction_and_pull() {
+        let [data_line] = steal_readable_pins(
+            Port::D,
+            [Pin::Two],
+            ReadablePinOptions {
+                function: Function::Digital,
+                pull: Pull::Neither,
+            },
+        )
+        .unwrap()
+        .into_pins();
+        simulated::with(|memory| memory.clear_accesses());
+
+        let mut data_line = data_line.into_flex();
+        data_line.set_as_output();
+        data_line.clear();
+        assert!(data_line.is_output());
+        let data_line = data_line.into_readable(Pull::Up);
+
+        simulated::with(|memory| {
+            assert_eq!(memory.value(Port::D.apb().direction().address()), 0);
+            assert_eq!(
+                memory.value(Port::D.apb().pull_up_select().address()),
+                0b100
+            );
+            assert!(!memory
+                .written_registers()
+                .contains(&Port::D.apb().lock().address()));
+        });
+
+        data_line.into_writable();
+        simulated::with(|memory| {
+            assert_eq!(memory.value(Port::D.apb().direction().address()), 0b100);
+        });
+
+        // The NMI pin PF0 (SW2 on the LaunchPad) has to be committed for its pull resistor to change
+        let [switch] = port_f_with_the_nmi_pin_unlocked()
+            .setup_writable_pins([Pin::Zero], WritablePinOptions::default())
+            .unwrap()
+            .into_pins();
+        simulated::with(|memory| memory.clear_accesses());
+
+        switch.into_readable(Pull::Up);
+
+        simulated::with(|memory| {
+            let writes = memory.written_registers();
+            let position = |address| {
+                writes
+                    .iter()
+                    .position(|written| *written == address)
+                    .unwrap()
+            };
+
+            assert_eq!(memory.value(Port::F.apb().pull_up_select().address()), 0b1);
+            assert!(
+                position(Port::F.apb().commit().address())
+                    < position(Port::F.apb().pull_up_select().address())
+            );
+            assert_eq!(writes.last(), Some(&Port::F.apb().lock().address()));
+            assert_eq!(memory.value(Port::F.apb().commit().address()), 0);
+        });
+    }
+
+    #[test]
+    fn setting_a_pin_goes_through_the_bit_band_alias() {
+        let [mut red, _blue] = steal_writable_pins(
+            Port::F,
+            [Pin::One, Pin::Two],
+            WritablePinOptions {
+                function: Function::Digital,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .into_pins();
+        simulated::with(|memory| memory.clear_accesses());
+
+        red.set();
+
+        let data = Port::F.apb().data(&[Pin::One, Pin::Two]).address();
+        let alias = crate::memory::bit_band::alias(data, 1).unwrap();
+        assert_eq!(alias as u32, 0x424A_0000 + 0x18 * 32 + 4);
+        simulated::with(|memory| {
+            assert_eq!(memory.writes(), [(alias, 1)]);
+            assert_eq!(memory.value(data), 0b0010);
+        });
+    }
 }
@@ -1,10 +1,18 @@
+use crate::registers::gpio::{
+    AFSEL, AMSEL, CR, DATA, DEN, DIR, DR2R, DR4R, DR8R, IBE, ICR, IEV, IM, IS, LOCK, MIS, ODR,
+    PCTL, PDR, PUR, RIS, SLR,
+};
+use crate::registers::{interrupts, peripherals, Register, RegisterSpec};
 use crate::{
-    memory, Board, Pin, ReadablePinOptions, ReadablePins, WritablePinOptions, WritablePins,
+    Board, FlexPin, Pin, ReadablePin, ReadablePinOptions, ReadablePins, WritablePin,
+    WritablePinOptions, WritablePins,
 };
 
-use super::pins::{setup_readable_pins, setup_writable_pins};
+use super::functions::{mux, Function};
+use super::interrupts as gpio_interrupts;
+use super::pins::{setup_readable_pins_on, setup_writable_pins_on};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Port {
     A,
     B,
@@ -14,51 +22,124 @@ pub enum Port {
     F,
 }
 
-pub struct PortOptions;
+/// Which of the two buses a port's registers are reached through
+///
+/// Page 258 of data sheet (GPIOHBCTL)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bus {
+    /// The legacy Advanced Peripheral Bus (APB), which every port starts out on
+    AdvancedPeripheral,
+    /// The Advanced High-Performance Bus (AHB), which is faster (better for bit-banging)
+    AdvancedHighPerformance,
+}
+
+pub struct PortOptions {
+    pub bus: Bus,
+    /// Allow setting up the pins that can be the non-maskable interrupt (PD7 and PF0), which are locked out of reset
+    /// so they can't be repurposed by accident
+    ///
+    /// Page 684 of data sheet (GPIOLOCK)
+    pub unlock_nmi_pins: bool,
+}
+
+impl Default for PortOptions {
+    /// The APB, which is what the ports use out of reset, with the NMI pins left locked
+    fn default() -> Self {
+        PortOptions {
+            bus: Bus::AdvancedPeripheral,
+            unlock_nmi_pins: false,
+        }
+    }
+}
 
 impl Port {
-    /// The starting point of memory addresses corresponding to this GPIO register
+    /// The port's registers, reached through the aperture of `bus`
     ///
     /// Modeled after page 660 of data sheet (GPIO Register Map)
-    const fn base(&self) -> u32 {
-        match self {
-            Port::A => 0x4000_4000,
-            Port::B => 0x4000_5000,
-            Port::C => 0x4000_6000,
-            Port::D => 0x4000_7000,
-            Port::E => 0x4002_4000,
-            Port::F => 0x4002_5000,
+    pub(super) const fn registers(&self, bus: Bus) -> PortRegisters {
+        let base = match (bus, self) {
+            (Bus::AdvancedPeripheral, Port::A) => peripherals::GPIO_PORTA,
+            (Bus::AdvancedPeripheral, Port::B) => peripherals::GPIO_PORTB,
+            (Bus::AdvancedPeripheral, Port::C) => peripherals::GPIO_PORTC,
+            (Bus::AdvancedPeripheral, Port::D) => peripherals::GPIO_PORTD,
+            (Bus::AdvancedPeripheral, Port::E) => peripherals::GPIO_PORTE,
+            (Bus::AdvancedPeripheral, Port::F) => peripherals::GPIO_PORTF,
+            (Bus::AdvancedHighPerformance, Port::A) => peripherals::GPIO_PORTA_AHB,
+            (Bus::AdvancedHighPerformance, Port::B) => peripherals::GPIO_PORTB_AHB,
+            (Bus::AdvancedHighPerformance, Port::C) => peripherals::GPIO_PORTC_AHB,
+            (Bus::AdvancedHighPerformance, Port::D) => peripherals::GPIO_PORTD_AHB,
+            (Bus::AdvancedHighPerformance, Port::E) => peripherals::GPIO_PORTE_AHB,
+            (Bus::AdvancedHighPerformance, Port::F) => peripherals::GPIO_PORTF_AHB,
+        };
+
+        PortRegisters {
+            port: *self,
+            bus,
+            base,
         }
     }
 
-    /// The memory address of the alternate function select (AFSEL) register for this port
+    /// The bus the port is on right now, according to GPIOHBCTL
+    ///
+    /// Page 258 of data sheet
+    pub(super) fn bus(&self) -> Bus {
+        if Board
+            .gpio_high_performance_bus_control()
+            .read_bit_atomic(self.high_performance_bus_control())
+        {
+            Bus::AdvancedHighPerformance
+        } else {
+            Bus::AdvancedPeripheral
+        }
+    }
+}
+
+#[cfg(test)]
+impl Port {
+    /// The port's registers on the APB, which is where the tests leave the ports
+    pub(super) const fn apb(&self) -> PortRegisters {
+        self.registers(Bus::AdvancedPeripheral)
+    }
+}
+
+/// A port's registers, reached through the aperture of one of the buses
+///
+/// A port can only be reached through the aperture of the bus it's on, so the bus is worked out once
+/// (when the port is set up) and kept here, instead of reading GPIOHBCTL for every register access
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct PortRegisters {
+    pub(super) port: Port,
+    pub(super) bus: Bus,
+    /// The starting point of memory addresses corresponding to this GPIO register
+    base: u32,
+}
+
+impl PortRegisters {
+    /// The alternate function select (AFSEL) register for this port
     ///
     /// Page 671 of data sheet
-    pub(super) const fn alternate_function_select(&self) -> *mut u32 {
-        const OFFSET: u32 = 0x420;
-        (self.base() + OFFSET) as *mut u32
+    pub(super) fn alternate_function_select(&self) -> Register<AFSEL> {
+        Register::new(self.base)
     }
 
-    /// The memory address of the analog mode select (AMSEL) register for this port
+    /// The analog mode select (AMSEL) register for this port
     ///
     /// Page 687 of data sheet
-    pub(super) const fn analog_mode_select(&self) -> *mut u32 {
-        const OFFSET: u32 = 0x52C;
-        (self.base() + OFFSET) as *mut u32
+    pub(super) fn analog_mode_select(&self) -> Register<AMSEL> {
+        Register::new(self.base)
     }
 
-    /// The memory address of the commit (CR) register for this port
+    /// The commit (CR) register for this port
     ///
     /// Page 685 of data sheet
-    pub(super) const fn commit(&self) -> *mut u32 {
-        const OFFSET: u32 = 0x524;
-        (self.base() + OFFSET) as *mut u32
+    pub(super) fn commit(&self) -> Register<CR> {
+        Register::new(self.base)
     }
 
-    /// The memory address of the data (DATA) register for this port
+    /// The data (DATA) register for this port
     ///
     /// Page 662 of data sheet
-    pub(super) fn data(&self, pins: &[Pin]) -> *mut u32 {
+    pub(super) fn data(&self, pins: &[Pin]) -> Register<DATA> {
         // Extra guidance provided by
         // http://shukra.cedt.iisc.ernet.in/edwiki/EmSys:TM4C123GXL_GPIO_-_Read_Write_Data_Register
         // because the data sheet was a bit hard to understand when thinking about why
@@ -70,57 +151,181 @@ impl Port {
             offset |= 1 << bit;
         }
 
-        (self.base() + offset) as *mut u32
+        // DATA's offset in the register map is the alias that has every pin's address bit set
+        Register::at(self.base + (DATA::OFFSET & offset))
     }
 
-    /// The memory address of the digital enable (DEN) register for this port
+    /// The digital enable (DEN) register for this port
     ///
     /// Page 682 of data sheet
-    pub(super) const fn digital_enable(&self) -> *mut u32 {
-        const OFFSET: u32 = 0x51C;
-        (self.base() + OFFSET) as *mut u32
+    pub(super) fn digital_enable(&self) -> Register<DEN> {
+        Register::new(self.base)
     }
 
-    /// The memory address of the direction (DIR) register for this port
+    /// The direction (DIR) register for this port
     ///
     /// Page 663 of data sheet
-    pub(super) const fn direction(&self) -> *mut u32 {
-        const OFFSET: u32 = 0x400;
-        (self.base() + OFFSET) as *mut u32
+    pub(super) fn direction(&self) -> Register<DIR> {
+        Register::new(self.base)
+    }
+
+    /// The 2-mA drive select (DR2R) register for this port
+    ///
+    /// Page 673 of data sheet
+    pub(super) fn two_milliamp_drive_select(&self) -> Register<DR2R> {
+        Register::new(self.base)
+    }
+
+    /// The 4-mA drive select (DR4R) register for this port
+    ///
+    /// Page 674 of data sheet
+    pub(super) fn four_milliamp_drive_select(&self) -> Register<DR4R> {
+        Register::new(self.base)
+    }
+
+    /// The 8-mA drive select (DR8R) register for this port
+    ///
+    /// Page 675 of data sheet
+    pub(super) fn eight_milliamp_drive_select(&self) -> Register<DR8R> {
+        Register::new(self.base)
+    }
+
+    /// The interrupt sense (IS) register for this port
+    ///
+    /// Page 664 of data sheet
+    pub(super) fn interrupt_sense(&self) -> Register<IS> {
+        Register::new(self.base)
+    }
+
+    /// The interrupt both edges (IBE) register for this port
+    ///
+    /// Page 665 of data sheet
+    pub(super) fn interrupt_both_edges(&self) -> Register<IBE> {
+        Register::new(self.base)
+    }
+
+    /// The interrupt event (IEV) register for this port
+    ///
+    /// Page 666 of data sheet
+    pub(super) fn interrupt_event(&self) -> Register<IEV> {
+        Register::new(self.base)
+    }
+
+    /// The interrupt mask (IM) register for this port
+    ///
+    /// Page 667 of data sheet
+    pub(super) fn interrupt_mask(&self) -> Register<IM> {
+        Register::new(self.base)
     }
 
-    /// The memory address of the lock (LOCK) register
+    /// The raw interrupt status (RIS) register for this port
+    ///
+    /// Page 668 of data sheet
+    #[allow(dead_code)]
+    pub(super) fn raw_interrupt_status(&self) -> Register<RIS> {
+        Register::new(self.base)
+    }
+
+    /// The masked interrupt status (MIS) register for this port
+    ///
+    /// Page 669 of data sheet
+    pub(super) fn masked_interrupt_status(&self) -> Register<MIS> {
+        Register::new(self.base)
+    }
+
+    /// The interrupt clear (ICR) register for this port
+    ///
+    /// Page 670 of data sheet
+    pub(super) fn interrupt_clear(&self) -> Register<ICR> {
+        Register::new(self.base)
+    }
+
+    /// The lock (LOCK) register
     ///
     /// Page 684 of data sheet
-    pub(super) const fn lock(&self) -> *mut u32 {
-        const OFFSET: u32 = 0x520;
-        (self.base() + OFFSET) as *mut u32
+    pub(super) fn lock(&self) -> Register<LOCK> {
+        Register::new(self.base)
+    }
+
+    /// The open drain select (ODR) register for this port
+    ///
+    /// Page 676 of data sheet
+    pub(super) fn open_drain_select(&self) -> Register<ODR> {
+        Register::new(self.base)
     }
 
-    /// The memory address of the port control (PCTL) register for this port
+    /// The port control (PCTL) register for this port
     ///
     /// Page 688 of data sheet
-    pub(super) const fn port_control(&self) -> *mut u32 {
-        const OFFSET: u32 = 0x52C;
-        (self.base() + OFFSET) as *mut u32
+    pub(super) fn port_control(&self) -> Register<PCTL> {
+        Register::new(self.base)
     }
 
-    /// The memory address of the pull-down resistor select (PDR) register for this port
+    /// The pull-down resistor select (PDR) register for this port
     /// Page 679 of data sheet
-    pub(super) const fn pull_down_select(&self) -> *mut u32 {
-        const OFFSET: u32 = 0x514;
-        (self.base() + OFFSET) as *mut u32
+    pub(super) fn pull_down_select(&self) -> Register<PDR> {
+        Register::new(self.base)
     }
 
-    /// The memory address of the pull-up resistor select (PUR) register for this port
+    /// The pull-up resistor select (PUR) register for this port
     /// Page 677 of data sheet
-    pub(super) const fn pull_up_select(&self) -> *mut u32 {
-        const OFFSET: u32 = 0x510;
-        (self.base() + OFFSET) as *mut u32
+    pub(super) fn pull_up_select(&self) -> Register<PUR> {
+        Register::new(self.base)
+    }
+
+    /// The slew rate control select (SLR) register for this port
+    ///
+    /// Page 681 of data sheet
+    pub(super) fn slew_rate_control_select(&self) -> Register<SLR> {
+        Register::new(self.base)
     }
 }
 
 impl Port {
+    /// This port's interrupt number in the NVIC
+    ///
+    /// Page 104 of data sheet (Interrupts)
+    pub(super) const fn interrupt(&self) -> u32 {
+        match self {
+            Port::A => interrupts::GPIOA,
+            Port::B => interrupts::GPIOB,
+            Port::C => interrupts::GPIOC,
+            Port::D => interrupts::GPIOD,
+            Port::E => interrupts::GPIOE,
+            Port::F => interrupts::GPIOF,
+        }
+    }
+
+    /// The pins of this port that can be the non-maskable interrupt (bit `n` for pin `n`)
+    ///
+    /// Page 650 of data sheet (Table 10-1, GPIO Pins With Special Considerations)
+    pub(super) const fn nmi_pins(&self) -> u8 {
+        match self {
+            Port::D => 1 << 7,
+            Port::F => 1 << 0,
+            _ => 0,
+        }
+    }
+
+    /// The pins of this port that the debugger uses for JTAG and SWD (bit `n` for pin `n`)
+    ///
+    /// Page 650 of data sheet (Table 10-1, GPIO Pins With Special Considerations)
+    pub(super) const fn debug_pins(&self) -> u8 {
+        match self {
+            Port::C => 0b1111,
+            _ => 0,
+        }
+    }
+
+    /// The pins of this port whose setup has to be committed while the port is unlocked (bit `n` for pin `n`)
+    ///
+    /// The JTAG and SWD pins are protected too, but they're never set up, so only the NMI pins are ever unlocked
+    ///
+    /// Page 685 of data sheet (GPIOCR)
+    pub(super) const fn protected_pins(&self) -> u8 {
+        self.nmi_pins()
+    }
+
     /// The corresponding bit for this port in the system's GPIO Run mode clock gating control (RCGCGPIO) register
     const fn run_mode_clock_gate_control(&self) -> u32 {
         match self {
@@ -132,37 +337,415 @@ impl Port {
             Port::F => 5,
         }
     }
+
+    /// The corresponding bit for this port in the system's GPIO high-performance bus control (GPIOHBCTL) register
+    const fn high_performance_bus_control(&self) -> u32 {
+        match self {
+            Port::A => 0,
+            Port::B => 1,
+            Port::C => 2,
+            Port::D => 3,
+            Port::E => 4,
+            Port::F => 5,
+        }
+    }
+}
+
+/// Why a port couldn't be set up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetupError {
+    /// The port is already set up
+    AlreadySetUp,
+}
+
+/// Why pins couldn't be set up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PinSetupError {
+    /// The pin is already set up (or was listed more than once)
+    AlreadySetUp(Pin),
+    /// The pin can't be used for the function (Table 23-5 on page 1351 of data sheet)
+    Unavailable(Pin, Function),
+    /// The pin can be the non-maskable interrupt, and the port wasn't set up with
+    /// [`PortOptions::unlock_nmi_pins`]
+    Locked(Pin),
+    /// The pin is used by the debugger (JTAG and SWD), and repurposing it would cut the debug connection
+    Debug(Pin),
 }
 
+/// A setup GPIO port that pins can be set up on
+///
+/// Each pin can only be set up once, until it's released back to the port
 pub struct UsablePort {
     port: Port,
+    registers: PortRegisters,
+    /// Bit `n` is set while pin `n` is set up
+    claimed_pins: u8,
+    unlock_nmi_pins: bool,
 }
 
 impl UsablePort {
+    /// Set up pins to be read from
+    ///
+    /// Fails if any of them are already set up (or listed twice), can't be used for the function,
+    /// or are protected (see [`PinSetupError`])
     pub fn setup_readable_pins<const N: usize>(
         &mut self,
         pins: [Pin; N],
         options: ReadablePinOptions,
-    ) -> ReadablePins<N> {
-        setup_readable_pins(self.port, pins, options)
+    ) -> Result<ReadablePins<N>, PinSetupError> {
+        check_pins(self.port, self.unlock_nmi_pins, &pins, options.function)?;
+        self.claim(&pins)?;
+
+        Ok(setup_readable_pins_on(
+            self.registers,
+            self.unlock_nmi_pins,
+            pins,
+            options,
+        ))
     }
 
+    /// Set up pins to be written to
+    ///
+    /// Fails if any of them are already set up (or listed twice), can't be used for the function,
+    /// or are protected (see [`PinSetupError`])
     pub fn setup_writable_pins<const N: usize>(
         &mut self,
         pins: [Pin; N],
         options: WritablePinOptions,
-    ) -> WritablePins<N> {
-        setup_writable_pins(self.port, pins, options)
+    ) -> Result<WritablePins<N>, PinSetupError> {
+        check_pins(self.port, self.unlock_nmi_pins, &pins, options.function)?;
+        self.claim(&pins)?;
+
+        Ok(setup_writable_pins_on(
+            self.registers,
+            self.unlock_nmi_pins,
+            pins,
+            options,
+        ))
+    }
+}
+
+impl UsablePort {
+    /// Give pins back to the port so they can be set up again
+    /// (they stay configured the way they were until then, except that their interrupts are disabled)
+    pub fn release_readable_pins<const N: usize>(&mut self, pins: ReadablePins<N>) {
+        for pin in pins.into_pins() {
+            self.release_readable_pin(pin);
+        }
+    }
+    pub fn release_readable_pin(&mut self, pin: ReadablePin) {
+        gpio_interrupts::disable(pin.registers, &[pin.pin as u32]);
+        gpio_interrupts::set_handler(pin.registers.port, pin.pin, None);
+        self.release(pin.registers.port, pin.pin);
+    }
+
+    /// Give pins back to the port so they can be set up again
+    /// (they stay configured the way they were until then)
+    pub fn release_writable_pins<const N: usize>(&mut self, pins: WritablePins<N>) {
+        for pin in pins.into_pins() {
+            self.release_writable_pin(pin);
+        }
+    }
+    pub fn release_writable_pin(&mut self, pin: WritablePin) {
+        self.release(pin.registers.port, pin.pin);
+    }
+
+    /// Give a pin back to the port so it can be set up again
+    /// (it stays configured the way it was until then)
+    pub fn release_flex_pin(&mut self, pin: FlexPin) {
+        self.release(pin.registers.port, pin.pin);
+    }
+
+    fn claim(&mut self, pins: &[Pin]) -> Result<(), PinSetupError> {
+        let mut bits = 0u8;
+
+        for pin in pins {
+            let bit = 1 << *pin as u8;
+
+            if (self.claimed_pins | bits) & bit != 0 {
+                return Err(PinSetupError::AlreadySetUp(*pin));
+            }
+
+            bits |= bit;
+        }
+
+        self.claimed_pins |= bits;
+        Ok(())
+    }
+
+    fn release(&mut self, port: Port, pin: Pin) {
+        assert!(
+            port == self.port,
+            "pins can only be released back to the port they were set up on"
+        );
+
+        self.claimed_pins &= !(1 << pin as u8);
+    }
+}
+
+/// Whether `pins` can be set up for `function` (leaving out whether they're already set up,
+/// which only the port's [`UsablePort`] keeps track of)
+pub(super) fn check_pins(
+    port: Port,
+    unlock_nmi_pins: bool,
+    pins: &[Pin],
+    function: Function,
+) -> Result<(), PinSetupError> {
+    let bit = |pin: &&Pin| 1 << **pin as u8;
+
+    if let Some(pin) = pins.iter().find(|pin| port.debug_pins() & bit(pin) != 0) {
+        return Err(PinSetupError::Debug(*pin));
+    }
+    if let Some(pin) = pins
+        .iter()
+        .find(|pin| !unlock_nmi_pins && port.nmi_pins() & bit(pin) != 0)
+    {
+        return Err(PinSetupError::Locked(*pin));
+    }
+
+    match pins.iter().find(|pin| mux(port, **pin, function).is_none()) {
+        Some(pin) => Err(PinSetupError::Unavailable(*pin, function)),
+        None => Ok(()),
     }
 }
 
-pub fn setup_port(board: Board, port: Port) -> UsablePort {
-    unsafe {
-        memory::set_bits(
-            board.gpio_run_mode_clock_gating_control(),
-            &[port.run_mode_clock_gate_control()],
+pub fn setup_port(board: Board, port: Port, options: PortOptions) -> UsablePort {
+    // Every register of the port moves to the other aperture, so this has to come before any of them are used
+    let high_performance_bus_control = board.gpio_high_performance_bus_control();
+    match options.bus {
+        Bus::AdvancedPeripheral => {
+            high_performance_bus_control.clear_bit_atomic(port.high_performance_bus_control())
+        }
+        Bus::AdvancedHighPerformance => {
+            high_performance_bus_control.set_bit_atomic(port.high_performance_bus_control())
+        }
+    }
+
+    board
+        .gpio_run_mode_clock_gating_control()
+        .set_bit_atomic(port.run_mode_clock_gate_control());
+
+    UsablePort {
+        port,
+        registers: port.registers(options.bus),
+        claimed_pins: 0,
+        unlock_nmi_pins: options.unlock_nmi_pins,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::simulated::{self, Access};
+    use crate::Pull;
+
+    /// The data sheet's addresses for the registers the GPIO driver uses, checked against the generated register map
+    #[test]
+    fn addresses_match_the_data_sheet() {
+        let ports = [
+            (Port::A, 0x4000_4000),
+            (Port::B, 0x4000_5000),
+            (Port::C, 0x4000_6000),
+            (Port::D, 0x4000_7000),
+            (Port::E, 0x4002_4000),
+            (Port::F, 0x4002_5000),
+        ];
+        assert_eq!(ports.map(|(port, _)| port.interrupt()), [0, 1, 2, 3, 4, 30]);
+
+        for (port, base) in ports {
+            let port = port.apb();
+            assert_eq!(port.base, base);
+            assert_eq!(port.direction().address() as u32, base + 0x400);
+            assert_eq!(port.interrupt_sense().address() as u32, base + 0x404);
+            assert_eq!(port.interrupt_both_edges().address() as u32, base + 0x408);
+            assert_eq!(port.interrupt_event().address() as u32, base + 0x40C);
+            assert_eq!(port.interrupt_mask().address() as u32, base + 0x410);
+            assert_eq!(port.raw_interrupt_status().address() as u32, base + 0x414);
+            assert_eq!(
+                port.masked_interrupt_status().address() as u32,
+                base + 0x418
+            );
+            assert_eq!(port.interrupt_clear().address() as u32, base + 0x41C);
+            assert_eq!(
+                port.alternate_function_select().address() as u32,
+                base + 0x420
+            );
+            assert_eq!(
+                port.two_milliamp_drive_select().address() as u32,
+                base + 0x500
+            );
+            assert_eq!(
+                port.four_milliamp_drive_select().address() as u32,
+                base + 0x504
+            );
+            assert_eq!(
+                port.eight_milliamp_drive_select().address() as u32,
+                base + 0x508
+            );
+            assert_eq!(port.open_drain_select().address() as u32, base + 0x50C);
+            assert_eq!(port.pull_up_select().address() as u32, base + 0x510);
+            assert_eq!(port.pull_down_select().address() as u32, base + 0x514);
+            assert_eq!(
+                port.slew_rate_control_select().address() as u32,
+                base + 0x518
+            );
+            assert_eq!(port.digital_enable().address() as u32, base + 0x51C);
+            assert_eq!(port.lock().address() as u32, base + 0x520);
+            assert_eq!(port.commit().address() as u32, base + 0x524);
+            assert_eq!(port.analog_mode_select().address() as u32, base + 0x528);
+            assert_eq!(port.port_control().address() as u32, base + 0x52C);
+            assert_eq!(port.data(&[]).address() as u32, base);
+            assert_eq!(
+                port.data(&[Pin::Zero, Pin::Seven]).address() as u32,
+                base + 0x204
+            );
+        }
+    }
+
+    #[test]
+    fn high_performance_bus_moves_every_register() {
+        let mut port = setup_port(
+            Board,
+            Port::B,
+            PortOptions {
+                bus: Bus::AdvancedHighPerformance,
+                ..Default::default()
+            },
         );
+
+        let high_performance_bus_control = Board.gpio_high_performance_bus_control().address();
+        assert_eq!(high_performance_bus_control as u32, 0x400F_E06C);
+        simulated::with(|memory| {
+            assert_eq!(memory.value(high_performance_bus_control), 0b10);
+        });
+        assert_eq!(Port::B.bus(), Bus::AdvancedHighPerformance);
+        assert_eq!(port.registers.base, 0x4005_9000);
+        assert_eq!(port.registers.direction().address() as u32, 0x4005_9400);
+
+        // Pins set up on the port use the faster aperture too
+        let [mut pin] = port
+            .setup_writable_pins([Pin::Three], WritablePinOptions::default())
+            .unwrap()
+            .into_pins();
+        simulated::with(|memory| {
+            assert_eq!(memory.value(0x4005_9400 as *mut u32), 0b1000);
+            assert_eq!(memory.value(0x4000_5400 as *mut u32), 0);
+            memory.clear_accesses();
+        });
+
+        // Without looking the bus up again
+        pin.set();
+        let mut pin = pin.into_flex();
+        pin.set_as_input(Pull::Up);
+        pin.set_as_output();
+        simulated::with(|memory| {
+            let bus_lookup = crate::memory::bit_band::alias(high_performance_bus_control, 1);
+            assert!(memory.accesses().iter().all(|access| match *access {
+                Access::Read(address, _) | Access::Write(address, _) =>
+                    address != high_performance_bus_control && Some(address) != bus_lookup,
+            }));
+            assert_eq!(memory.value(0x4005_9510 as *mut u32), 0b1000);
+        });
+
+        setup_port(Board, Port::B, PortOptions::default());
+        assert_eq!(Port::B.bus(), Bus::AdvancedPeripheral);
+    }
+
+    #[test]
+    fn pins_can_only_be_set_up_once_until_released() {
+        let mut port = setup_port(Board, Port::F, PortOptions::default());
+        let options = || WritablePinOptions {
+            function: Function::Digital,
+            ..Default::default()
+        };
+
+        let led = port.setup_writable_pins([Pin::One, Pin::Two], options());
+        assert!(led.is_ok());
+        assert_eq!(
+            port.setup_readable_pins(
+                [Pin::Two],
+                ReadablePinOptions {
+                    function: Function::Digital,
+                    pull: Pull::Up,
+                },
+            )
+            .err(),
+            Some(PinSetupError::AlreadySetUp(Pin::Two))
+        );
+        assert_eq!(
+            port.setup_writable_pins([Pin::Three, Pin::Three], options())
+                .err(),
+            Some(PinSetupError::AlreadySetUp(Pin::Three))
+        );
+
+        port.release_writable_pins(led.unwrap());
+        assert!(port.setup_writable_pins([Pin::Two], options()).is_ok());
+    }
+
+    #[test]
+    fn pins_can_only_be_set_up_for_their_own_functions() {
+        let mut port = setup_port(Board, Port::A, PortOptions::default());
+
+        assert_eq!(
+            port.setup_writable_pins(
+                [Pin::One],
+                WritablePinOptions {
+                    function: Function::U1Tx,
+                    ..Default::default()
+                },
+            )
+            .err(),
+            Some(PinSetupError::Unavailable(Pin::One, Function::U1Tx))
+        );
+
+        // Nothing is claimed when setup fails
+        assert!(port
+            .setup_writable_pins(
+                [Pin::One],
+                WritablePinOptions {
+                    function: Function::U0Tx,
+                    ..Default::default()
+                },
+            )
+            .is_ok());
     }
 
-    UsablePort { port }
+    #[test]
+    fn protected_pins_need_unlocking_and_debug_pins_are_refused() {
+        let mut port = setup_port(Board, Port::D, PortOptions::default());
+        assert_eq!(
+            port.setup_writable_pins([Pin::Six, Pin::Seven], WritablePinOptions::default())
+                .err(),
+            Some(PinSetupError::Locked(Pin::Seven))
+        );
+
+        let mut port = setup_port(
+            Board,
+            Port::D,
+            PortOptions {
+                unlock_nmi_pins: true,
+                ..Default::default()
+            },
+        );
+        assert!(port
+            .setup_writable_pins([Pin::Seven], WritablePinOptions::default())
+            .is_ok());
+
+        let mut port = setup_port(
+            Board,
+            Port::C,
+            PortOptions {
+                unlock_nmi_pins: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            port.setup_writable_pins([Pin::Four, Pin::Three], WritablePinOptions::default())
+                .err(),
+            Some(PinSetupError::Debug(Pin::Three))
+        );
+        assert!(port
+            .setup_writable_pins([Pin::Four], WritablePinOptions::default())
+            .is_ok());
+    }
 }
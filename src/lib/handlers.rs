@@ -0,0 +1,175 @@
+//! The panic handler, allocator, and allocation error handler used on the board
+//!
+//! The fault handlers can run anywhere (even in an interrupt handler or a critical section),
+//! so they leave the clock alone, keep interrupts disabled, and only poll and count:
+//! the message goes out by polling UART0 and the LED blinks with a busy-wait
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+use core::ptr;
+
+use crate::gpio::pins::{steal_readable_pins, steal_writable_pins};
+use crate::gpio::ports::setup_port as setup_gpio_port;
+use crate::launchpad::BAUD_RATE;
+use crate::rgb_led::{Color, RgbLed, Step};
+use crate::time::Duration;
+use crate::uart::setup_port as setup_uart_port;
+use crate::{
+    interrupts, time, Board, Function, GPIOPort, GPIOPortOptions, Pin, Pull, ReadablePinOptions,
+    UARTParity, UARTPort, UARTPortOptions, UARTStopBits, UsableUARTPort, WordLength, WritablePin,
+    WritablePinOptions,
+};
+
+/// How long each color (and each gap between them) is shown while flashing
+const FLASH_MILLIS: u64 = 250;
+
+/// Take the RGB LED's pins back as plain digital outputs (whether or not they were PWM outputs)
+fn setup_rgb_led() -> Option<RgbLed> {
+    setup_gpio_port(Board, GPIOPort::F, GPIOPortOptions::default());
+
+    let pins = steal_writable_pins(
+        GPIOPort::F,
+        [Pin::One, Pin::Three, Pin::Two],
+        WritablePinOptions::default(),
+    )
+    .ok()?;
+
+    Some(RgbLed::software(pins))
+}
+
+/// Flash between `first` and `second` forever, with the LED off in between
+fn flash(rgb_led: Option<RgbLed>, first: Color, second: Color) -> ! {
+    let Some(mut rgb_led) = rgb_led else {
+        loop {
+            core::hint::spin_loop();
+        }
+    };
+
+    // Timed by SysTick's counter (which doesn't need interrupts) once the board has set it up
+    if time::systick_enabled() {
+        let flash = Duration::from_millis(FLASH_MILLIS);
+
+        rgb_led.repeat(&[
+            Step::Hold(first, flash),
+            Step::Hold(Color::BLACK, flash),
+            Step::Hold(second, flash),
+            Step::Hold(Color::BLACK, flash),
+        ]);
+    }
+
+    // SysTick hasn't been set up, so there's nothing to time the flashes with but counting
+    loop {
+        for color in [first, Color::BLACK, second, Color::BLACK] {
+            rgb_led.set(color);
+
+            for _ in 0..1_000_000 {
+                core::hint::spin_loop();
+            }
+        }
+    }
+}
+
+/// UART0 (the debug port) set up again for polling, at whatever the system clock is running at
+struct DebugUart {
+    port: UsableUARTPort,
+    transmit_pin: WritablePin,
+}
+
+impl Write for DebugUart {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        self.port.write_string(&mut self.transmit_pin, string);
+        Ok(())
+    }
+}
+
+fn setup_debug_uart() -> Option<DebugUart> {
+    setup_gpio_port(Board, GPIOPort::A, GPIOPortOptions::default());
+
+    steal_readable_pins(
+        GPIOPort::A,
+        [Pin::Zero],
+        ReadablePinOptions {
+            function: Function::U0Rx,
+            pull: Pull::Neither,
+        },
+    )
+    .ok()?;
+    let [transmit_pin] = steal_writable_pins(
+        GPIOPort::A,
+        [Pin::One],
+        WritablePinOptions {
+            function: Function::U0Tx,
+            ..Default::default()
+        },
+    )
+    .ok()?
+    .into_pins();
+
+    let port = setup_uart_port(
+        Board,
+        UARTPort::Zero,
+        UARTPortOptions {
+            baud_rate: BAUD_RATE,
+            fifos: true,
+            word_length: WordLength::Eight,
+            parity: UARTParity::None,
+            stop_bits: UARTStopBits::One,
+        },
+        time::system_clock_hz(),
+    )
+    .ok()?;
+
+    Some(DebugUart { port, transmit_pin })
+}
+
+#[panic_handler]
+fn panic(panic_info: &PanicInfo) -> ! {
+    interrupts::disable_all();
+
+    // Red in case sending the message hangs and the flashing is never reached
+    let mut rgb_led = setup_rgb_led();
+    if let Some(rgb_led) = &mut rgb_led {
+        rgb_led.set(Color::RED);
+    }
+
+    if let Some(mut uart_0) = setup_debug_uart() {
+        // https://gist.github.com/fnky/458719343aabd01cfb17a3a4f7296797
+        // (written straight out, so a full heap can't get in the way)
+        let _ = write!(uart_0, "\x1b[31m\r\n{panic_info}\r\n\x1b[0m\r\n");
+    }
+
+    flash(rgb_led, Color::RED, Color::CYAN)
+}
+
+struct BumpPointerAlloc;
+const HEAP_SIZE: usize = 0x1000;
+static mut HEAP: [u8; HEAP_SIZE] = [0; HEAP_SIZE];
+static mut USED: usize = 0;
+
+#[global_allocator]
+static ALLOCATOR: BumpPointerAlloc = BumpPointerAlloc;
+
+unsafe impl GlobalAlloc for BumpPointerAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let size = layout.size();
+
+        if USED + size > HEAP_SIZE {
+            ptr::null_mut()
+        } else {
+            let pointer = ptr::addr_of_mut!(HEAP).cast::<u8>().add(USED);
+            USED += size;
+
+            pointer
+        }
+    }
+
+    unsafe fn dealloc(&self, _: *mut u8, _: Layout) {}
+}
+
+#[alloc_error_handler]
+fn alloc_error(_cause: Layout) -> ! {
+    interrupts::disable_all();
+
+    flash(setup_rgb_led(), Color::YELLOW, Color::RED)
+}
@@ -0,0 +1,291 @@
+//! Masking interrupts
+//!
+//! Read-modify-writes of registers that interrupt handlers also touch have to happen with
+//! those interrupts masked, or the handler's change can be lost between the read and the write.
+//! [`free`] masks every interrupt (with PRIMASK) and [`mask`] masks only the interrupts
+//! at or below a priority level (with BASEPRI), leaving more urgent ones running
+//!
+//! Each peripheral interrupt also has to be enabled in the NVIC ([`enable`]) before it does anything.
+//! Interrupt numbers are in Table 2-9 on page 104 of data sheet
+//! (and `registers::interrupts` for the ones in the register map)
+
+use core::cell::UnsafeCell;
+
+use crate::registers::core_peripherals::{BASE, DIS, EN, PRI};
+use crate::registers::{Register, RegisterSpec};
+
+/// Proof that interrupts are disabled, handed to the closure run by [`free`]
+pub struct CriticalSection {
+    _private: (),
+}
+
+/// Run `f` with every (maskable) interrupt disabled, then restore the previous state
+///
+/// Nesting is fine: interrupts are only re-enabled if they were enabled to begin with
+pub fn free<R>(f: impl FnOnce(&CriticalSection) -> R) -> R {
+    let were_disabled = cpu::primask();
+    cpu::disable();
+
+    let result = f(&CriticalSection { _private: () });
+
+    if !were_disabled {
+        cpu::enable();
+    }
+
+    result
+}
+
+/// The number of priority bits implemented by the NVIC (the top 3 bits of each 8-bit priority)
+pub(crate) const PRIORITY_BITS: u32 = 3;
+
+/// Run `f` with interrupts of priority level `level` (0 is the most urgent, 7 the least) and below masked,
+/// then restore the previous mask
+///
+/// The mask is only ever raised: if a stricter mask is already in place (when nested), it stays
+///
+/// Panics if `level` isn't 1 through 7 (level 0 can't be masked with BASEPRI, use [`free`] instead)
+pub fn mask<R>(level: u8, f: impl FnOnce() -> R) -> R {
+    assert!(
+        (1..=7).contains(&level),
+        "priority level must be 1 through 7"
+    );
+
+    let previous = cpu::basepri();
+    cpu::basepri_max((level as u32) << (8 - PRIORITY_BITS));
+
+    let result = f();
+
+    cpu::set_basepri(previous);
+
+    result
+}
+
+/// Disable every (maskable) interrupt for good, for fault handlers that take the board over and never give it back
+#[cfg(not(test))]
+pub(crate) fn disable_all() {
+    cpu::disable();
+}
+
+/// Whether interrupts are currently disabled (PRIMASK is set)
+pub fn disabled() -> bool {
+    cpu::primask()
+}
+
+/// Data shared between the program and interrupt handlers, only reachable inside a critical section
+///
+/// There's only one core, so nothing else can run while interrupts are disabled
+pub struct Mutex<T> {
+    value: UnsafeCell<T>,
+}
+
+impl<T> Mutex<T> {
+    pub const fn new(value: T) -> Self {
+        Mutex {
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn borrow<'cs>(&'cs self, _critical_section: &'cs CriticalSection) -> &'cs T {
+        unsafe { &*self.value.get() }
+    }
+}
+
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+/// The NVIC register with the bit for interrupt `number`
+const fn nvic_register<S: RegisterSpec>(number: u32) -> Register<S> {
+    Register::at(BASE + S::OFFSET + 4 * (number / 32))
+}
+
+/// Enable interrupt `number` in the NVIC
+pub fn enable(number: u32) {
+    // Writing 0s has no effect, so there's no need to read-modify-write
+    nvic_register::<EN>(number).write(1 << (number % 32));
+}
+
+/// Disable interrupt `number` in the NVIC
+pub fn disable(number: u32) {
+    nvic_register::<DIS>(number).write(1 << (number % 32));
+}
+
+/// Set the priority level of interrupt `number` (0 is the most urgent, 7 the least)
+///
+/// Panics if `level` is more than 7
+pub fn set_priority(number: u32, level: u8) {
+    assert!(level <= 7, "priority level must be 0 through 7");
+
+    let priority: Register<PRI> = Register::at(BASE + PRI::OFFSET + 4 * (number / 4));
+    // Only the top bits of each interrupt's byte are implemented
+    let shift = 8 * (number % 4) + (8 - PRIORITY_BITS);
+
+    priority.update_critical(&|current| (current & !(0b111 << shift)) | ((level as u32) << shift));
+}
+
+#[cfg(all(target_arch = "arm", not(test)))]
+mod cpu {
+    use core::arch::asm;
+
+    pub fn primask() -> bool {
+        let primask: u32;
+        unsafe {
+            asm!("mrs {}, PRIMASK", out(reg) primask, options(nomem, nostack, preserves_flags));
+        }
+        primask & 1 != 0
+    }
+
+    pub fn disable() {
+        // No `nomem` so that the compiler doesn't move memory accesses out of the critical section
+        unsafe {
+            asm!("cpsid i", options(nostack, preserves_flags));
+        }
+    }
+
+    pub fn enable() {
+        unsafe {
+            asm!("cpsie i", options(nostack, preserves_flags));
+        }
+    }
+
+    pub fn basepri() -> u32 {
+        let basepri: u32;
+        unsafe {
+            asm!("mrs {}, BASEPRI", out(reg) basepri, options(nomem, nostack, preserves_flags));
+        }
+        basepri
+    }
+
+    pub fn set_basepri(basepri: u32) {
+        unsafe {
+            asm!("msr BASEPRI, {}", in(reg) basepri, options(nostack, preserves_flags));
+        }
+    }
+
+    /// Only changes BASEPRI if the new value masks more than the current one
+    pub fn basepri_max(basepri: u32) {
+        unsafe {
+            asm!("msr BASEPRI_MAX, {}", in(reg) basepri, options(nostack, preserves_flags));
+        }
+    }
+}
+
+/// Nothing to mask off the microcontroller (this is only so the library can be checked on the host)
+#[cfg(all(not(target_arch = "arm"), not(test)))]
+mod cpu {
+    pub fn primask() -> bool {
+        false
+    }
+
+    pub fn disable() {}
+
+    pub fn enable() {}
+
+    pub fn basepri() -> u32 {
+        0
+    }
+
+    pub fn set_basepri(_: u32) {}
+
+    pub fn basepri_max(_: u32) {}
+}
+
+/// PRIMASK and BASEPRI for the host, kept per test thread
+#[cfg(test)]
+mod cpu {
+    use core::cell::Cell;
+
+    std::thread_local! {
+        static PRIMASK: Cell<bool> = const { Cell::new(false) };
+        static BASEPRI: Cell<u32> = const { Cell::new(0) };
+    }
+
+    pub fn primask() -> bool {
+        PRIMASK.with(Cell::get)
+    }
+
+    pub fn disable() {
+        PRIMASK.with(|primask| primask.set(true));
+    }
+
+    pub fn enable() {
+        PRIMASK.with(|primask| primask.set(false));
+    }
+
+    pub fn basepri() -> u32 {
+        BASEPRI.with(Cell::get)
+    }
+
+    pub fn set_basepri(basepri: u32) {
+        BASEPRI.with(|current| current.set(basepri));
+    }
+
+    pub fn basepri_max(basepri: u32) {
+        BASEPRI.with(|current| {
+            if current.get() == 0 || basepri < current.get() {
+                current.set(basepri);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_disables_then_restores() {
+        assert!(!disabled());
+
+        free(|_| {
+            assert!(disabled());
+
+            free(|_| assert!(disabled()));
+
+            // The inner critical section must not re-enable interrupts for the outer one
+            assert!(disabled());
+        });
+
+        assert!(!disabled());
+    }
+
+    #[test]
+    fn mask_only_raises_then_restores() {
+        mask(4, || {
+            assert_eq!(cpu::basepri(), 4 << 5);
+
+            mask(6, || assert_eq!(cpu::basepri(), 4 << 5));
+            mask(2, || assert_eq!(cpu::basepri(), 2 << 5));
+
+            assert_eq!(cpu::basepri(), 4 << 5);
+        });
+
+        assert_eq!(cpu::basepri(), 0);
+    }
+
+    #[test]
+    fn nvic_bits_are_spread_over_registers() {
+        use crate::memory::simulated;
+
+        enable(30);
+        enable(33);
+        disable(33);
+        set_priority(30, 5);
+
+        simulated::with(|memory| {
+            assert_eq!(
+                memory.writes(),
+                [
+                    (0xE000_E100 as *mut u32, 1 << 30),
+                    (0xE000_E104 as *mut u32, 1 << 1),
+                    (0xE000_E184 as *mut u32, 1 << 1),
+                    (0xE000_E41C as *mut u32, 5 << 21),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn level_zero_cant_be_masked() {
+        mask(0, || {});
+    }
+}
@@ -0,0 +1,225 @@
+//! Board support for the EK-TM4C123GXL LaunchPad
+//!
+//! Sets up what's wired up on the LaunchPad itself: the two user switches, the RGB LED,
+//! and UART0, which the on-board debugger (the ICDI) passes through to the computer as a virtual COM port.
+//! The rest of the board (and the rest of ports A and F) is handed back for everything else
+//!
+//! Page 9 of the LaunchPad's user's guide (User Switches and RGB User LED)
+//! and page 10 (Virtual COM Port): https://www.ti.com/lit/ug/spmu296/spmu296.pdf
+
+use alloc::string::String;
+
+use crate::rgb_led::RgbLed;
+use crate::time::Duration;
+use crate::{
+    setup_board, steal_board, BoardOptions, Function, GPIOPort, GPIOPortOptions, PWMModule,
+    PWMOutput, PWMOutputOptions, Pin, Pull, ReadablePin, ReadablePinOptions, UARTErrorCounts,
+    UARTInterruptOptions, UARTParity, UARTPort, UARTPortOptions, UARTReceiveError, UARTStopBits,
+    UsableBoard, UsableGPIOPort, UsableUARTPort, WordLength, WritablePin, WritablePinOptions,
+};
+
+/// The baud rate the virtual COM port is set up with
+pub const BAUD_RATE: u32 = 115_200;
+
+/// The frequency the RGB LED's PWM outputs run at (far too fast to see flicker)
+pub const RGB_LED_PWM_HZ: u32 = 1_000;
+
+/// The LaunchPad with its on-board peripherals set up
+pub struct LaunchPad {
+    /// For setting up the ports the LaunchPad doesn't use itself
+    pub board: UsableBoard,
+    /// The rest of port A (PA0 and PA1 are used by `uart0`)
+    pub port_a: UsableGPIOPort,
+    /// The rest of port F (PF0 to PF4 are used by the switches and the RGB LED)
+    pub port_f: UsableGPIOPort,
+    /// SW1 on PF4, which reads low while it's pressed
+    pub sw1: ReadablePin,
+    /// SW2 on PF0, which reads low while it's pressed
+    pub sw2: ReadablePin,
+    /// The RGB LED, with its red (PF1), blue (PF2), and green (PF3) channels driven by
+    /// PWM module 1's outputs 5, 6, and 7
+    pub rgb_led: RgbLed,
+    /// UART0 on PA0 and PA1 (8 data bits, no parity, 1 stop bit), which shows up on the computer
+    /// as the debugger's virtual COM port, with its interrupts enabled so it's buffered
+    pub uart0: DebugUart,
+}
+
+/// UART0 together with its pins
+pub struct DebugUart {
+    pub port: UsableUARTPort,
+    pub receive_pin: ReadablePin,
+    pub transmit_pin: WritablePin,
+}
+
+impl DebugUart {
+    /// Read as many bytes as have been received (up to `bytes.len()`) without waiting, returning how many that was
+    ///
+    /// A byte that came in wrong stops the read there (see [`UsableUARTPort::read`])
+    pub fn read(&mut self, bytes: &mut [u8]) -> Result<usize, UARTReceiveError> {
+        self.port.read(&self.receive_pin, bytes)
+    }
+    /// Write as many of `bytes` as there's room for without waiting, returning how many that was
+    pub fn write(&mut self, bytes: &[u8]) -> usize {
+        self.port.write(&mut self.transmit_pin, bytes)
+    }
+
+    pub fn read_byte(&self, blocking: bool) -> Option<Result<u8, UARTReceiveError>> {
+        self.port.read_byte(&self.receive_pin, blocking)
+    }
+    pub fn write_byte(&mut self, byte: u8, blocking: bool) -> bool {
+        self.port.write_byte(&mut self.transmit_pin, byte, blocking)
+    }
+
+    pub fn write_string(&mut self, string: &str) {
+        self.port.write_string(&mut self.transmit_pin, string);
+    }
+    pub fn write_line(&mut self, string: &str) {
+        self.port.write_line(&mut self.transmit_pin, string);
+    }
+    pub fn error_counts(&self) -> UARTErrorCounts {
+        self.port.error_counts()
+    }
+    pub fn clear_errors(&mut self) {
+        self.port.clear_errors();
+    }
+
+    pub fn send_break(&mut self, duration: Duration) {
+        self.port.send_break(&mut self.transmit_pin, duration);
+    }
+
+    pub fn read_line(&mut self) -> String {
+        self.port
+            .read_line(&mut self.transmit_pin, &self.receive_pin)
+    }
+}
+
+/// Set up the board and the LaunchPad's peripherals
+///
+/// Like [`setup_board`], this only works once: every call after the first returns `None`
+pub fn setup_launchpad(options: BoardOptions) -> Option<LaunchPad> {
+    setup_board(options).map(setup_peripherals)
+}
+
+/// Set up the board and the LaunchPad's peripherals whether or not they've already been set up
+///
+/// # Safety
+///
+/// The same as [`steal_board`]
+pub unsafe fn steal_launchpad(options: BoardOptions) -> LaunchPad {
+    setup_peripherals(steal_board(options))
+}
+
+fn setup_peripherals(mut board: UsableBoard) -> LaunchPad {
+    // The board is brand new, so none of its ports or pins can have been claimed yet
+    let mut port_f = board
+        .setup_gpio_port(
+            GPIOPort::F,
+            GPIOPortOptions {
+                // SW2 is on PF0, which can also be the NMI
+                unlock_nmi_pins: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let [sw2, sw1] = port_f
+        .setup_readable_pins(
+            [Pin::Zero, Pin::Four],
+            ReadablePinOptions {
+                function: Function::Digital,
+                // The switches connect the pins to ground when pressed and leave them floating otherwise
+                pull: Pull::Up,
+            },
+        )
+        .unwrap()
+        .into_pins();
+    let rgb_led = setup_rgb_led(&mut board, &mut port_f);
+
+    let mut port_a = board
+        .setup_gpio_port(GPIOPort::A, GPIOPortOptions::default())
+        .unwrap();
+
+    let [receive_pin] = port_a
+        .setup_readable_pins(
+            [Pin::Zero],
+            ReadablePinOptions {
+                function: Function::U0Rx,
+                pull: Pull::Neither,
+            },
+        )
+        .unwrap()
+        .into_pins();
+    let [transmit_pin] = port_a
+        .setup_writable_pins(
+            [Pin::One],
+            WritablePinOptions {
+                function: Function::U0Tx,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .into_pins();
+    let mut port = board
+        .setup_uart_port(
+            UARTPort::Zero,
+            UARTPortOptions {
+                baud_rate: BAUD_RATE,
+                fifos: true,
+                word_length: WordLength::Eight,
+                parity: UARTParity::None,
+                stop_bits: UARTStopBits::One,
+            },
+        )
+        .unwrap();
+    // So nothing typed is lost while the program is busy with something else
+    port.enable_interrupts(UARTInterruptOptions::default());
+
+    LaunchPad {
+        board,
+        port_a,
+        port_f,
+        sw1,
+        sw2,
+        rgb_led,
+        uart0: DebugUart {
+            port,
+            receive_pin,
+            transmit_pin,
+        },
+    }
+}
+
+fn setup_rgb_led(board: &mut UsableBoard, port_f: &mut UsableGPIOPort) -> RgbLed {
+    // Red, green, blue
+    let channels = [
+        (Pin::One, Function::M1PWM5, 5),
+        (Pin::Three, Function::M1PWM7, 7),
+        (Pin::Two, Function::M1PWM6, 6),
+    ];
+
+    let pins = channels.map(|(pin, function, _)| {
+        let [pin] = port_f
+            .setup_writable_pins(
+                [pin],
+                WritablePinOptions {
+                    function,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .into_pins();
+        pin
+    });
+    let outputs = channels.map(|(_, _, number)| {
+        board
+            .setup_pwm_output(
+                PWMOutput::new(PWMModule::One, number),
+                PWMOutputOptions {
+                    frequency_hz: RGB_LED_PWM_HZ,
+                },
+            )
+            .unwrap()
+    });
+
+    RgbLed::pwm(outputs, pins)
+}
@@ -0,0 +1,112 @@
+//! Bit-banding
+//!
+//! The Cortex-M4 maps every bit of the first megabyte of SRAM and of the peripheral region
+//! to its own word in an alias region. Writing 0 or 1 to an alias word clears or sets just that bit
+//! (the processor does the read-modify-write itself, without it being interruptible),
+//! and reading it reads just that bit
+//!
+//! Page 97 of data sheet (Bit-Banding)
+
+use core::ops::Range;
+
+/// The SRAM that can be bit-banded
+const SRAM: Range<u32> = 0x2000_0000..0x2010_0000;
+/// Where the bit-band alias of SRAM starts
+const SRAM_ALIAS: u32 = 0x2200_0000;
+
+/// The peripherals that can be bit-banded
+const PERIPHERALS: Range<u32> = 0x4000_0000..0x4010_0000;
+/// Where the bit-band alias of the peripherals starts
+const PERIPHERALS_ALIAS: u32 = 0x4200_0000;
+
+/// Each bit of the region takes up one word (4 bytes) of the alias, so each byte takes up 32
+const ALIAS_BYTES_PER_BYTE: u32 = 32;
+const ALIAS_BYTES_PER_BIT: u32 = 4;
+
+/// The alias word for `bit` of the word at `address`, or `None` if `address` can't be bit-banded
+pub fn alias(address: *const u32, bit: u32) -> Option<*mut u32> {
+    let address = address as u32;
+
+    [(SRAM, SRAM_ALIAS), (PERIPHERALS, PERIPHERALS_ALIAS)]
+        .into_iter()
+        .find(|(region, _)| region.contains(&address))
+        .filter(|_| bit < 32)
+        .map(|(region, alias)| {
+            let byte_offset = address - region.start;
+            (alias + byte_offset * ALIAS_BYTES_PER_BYTE + bit * ALIAS_BYTES_PER_BIT) as *mut u32
+        })
+}
+
+/// The word and bit that `alias` stands for, or `None` if `alias` isn't in a bit-band alias region
+#[cfg(test)]
+pub fn target(alias: *const u32) -> Option<(*mut u32, u32)> {
+    let alias = alias as u32;
+
+    [(SRAM, SRAM_ALIAS), (PERIPHERALS, PERIPHERALS_ALIAS)]
+        .into_iter()
+        .map(|(region, alias_start)| {
+            let alias_end = alias_start + region.len() as u32 * ALIAS_BYTES_PER_BYTE;
+            (region, alias_start..alias_end)
+        })
+        .find(|(_, aliases)| aliases.contains(&alias))
+        .map(|(region, aliases)| {
+            let alias_offset = alias - aliases.start;
+            let byte_offset = alias_offset / ALIAS_BYTES_PER_BYTE;
+            let bit = (alias_offset % ALIAS_BYTES_PER_BYTE) / ALIAS_BYTES_PER_BIT;
+            // The alias of a byte that isn't word-aligned belongs to the word containing it
+            let word = (region.start + byte_offset) & !0b11;
+            let bit = bit + (byte_offset % 4) * 8;
+            (word as *mut u32, bit)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peripheral_alias() {
+        // GPIO Port F's DATA register (every pin's address bit set), pin 1
+        let data = 0x4002_53FC as *const u32;
+        assert_eq!(alias(data, 1), Some(0x424A_7F84 as *mut u32));
+
+        // RCGCGPIO, port F
+        let rcgcgpio = 0x400F_E608 as *const u32;
+        assert_eq!(alias(rcgcgpio, 5), Some(0x43FC_C114 as *mut u32));
+    }
+
+    #[test]
+    fn sram_alias() {
+        assert_eq!(
+            alias(0x2000_0000 as *const u32, 0),
+            Some(0x2200_0000 as *mut u32)
+        );
+        assert_eq!(
+            alias(0x2000_0004 as *const u32, 3),
+            Some(0x2200_008C as *mut u32)
+        );
+        assert_eq!(
+            alias(0x200F_FFFC as *const u32, 31),
+            Some(0x23FF_FFFC as *mut u32)
+        );
+    }
+
+    #[test]
+    fn outside_the_regions() {
+        assert_eq!(alias(0x0000_1000 as *const u32, 0), None);
+        assert_eq!(alias(0x2010_0000 as *const u32, 0), None);
+        assert_eq!(alias(0x4010_0000 as *const u32, 0), None);
+        assert_eq!(alias(0xE000_E100 as *const u32, 0), None);
+        assert_eq!(alias(0x4000_4000 as *const u32, 32), None);
+    }
+
+    #[test]
+    fn target_undoes_alias() {
+        for (address, bit) in [(0x4002_53FC, 1), (0x400F_E608, 5), (0x2000_7FFC, 31)] {
+            let alias = alias(address as *const u32, bit).unwrap();
+            assert_eq!(target(alias), Some((address as *mut u32, bit)));
+        }
+
+        assert_eq!(target(0x4000_4000 as *const u32), None);
+    }
+}
@@ -0,0 +1,221 @@
+//! Interact with memory
+//!
+//! Every register access in the library goes through the free functions in this module,
+//! which forward to a [`Backend`]. On the board that is [`Mmio`] (volatile reads and writes),
+//! and under `cargo test` on the host it is the simulated address space in [`simulated`]
+
+use core::ptr;
+
+use crate::interrupts;
+
+pub mod bit_band;
+#[cfg(test)]
+pub mod simulated;
+
+/// A way of reading and writing 32-bit registers
+pub trait Backend {
+    /// # Safety
+    /// `address` must point to a readable register
+    unsafe fn read(&self, address: *const u32) -> u32;
+    /// # Safety
+    /// `address` must point to a writable register
+    unsafe fn write(&self, address: *mut u32, new: u32);
+}
+
+/// Memory-mapped I/O: the real registers on the board
+#[cfg_attr(test, allow(dead_code))]
+pub struct Mmio;
+
+impl Backend for Mmio {
+    unsafe fn read(&self, address: *const u32) -> u32 {
+        ptr::read_volatile(address)
+    }
+    unsafe fn write(&self, address: *mut u32, new: u32) {
+        ptr::write_volatile(address, new);
+    }
+}
+
+#[cfg(not(test))]
+fn with_backend<R>(f: impl FnOnce(&dyn Backend) -> R) -> R {
+    f(&Mmio)
+}
+#[cfg(test)]
+fn with_backend<R>(f: impl FnOnce(&dyn Backend) -> R) -> R {
+    simulated::with(|memory| f(memory))
+}
+
+pub unsafe fn read(address: *const u32) -> u32 {
+    with_backend(|backend| backend.read(address))
+}
+pub unsafe fn write(address: *mut u32, new: u32) {
+    with_backend(|backend| backend.write(address, new));
+}
+
+pub unsafe fn update(address: *mut u32, updater: &dyn Fn(u32) -> u32) {
+    write(address, updater(read(address)));
+}
+/// Like [`update`], but with interrupts disabled so that an interrupt handler
+/// can't change the register between the read and the write
+pub unsafe fn update_critical(address: *mut u32, updater: &dyn Fn(u32) -> u32) {
+    interrupts::free(|_| update(address, updater));
+}
+
+pub unsafe fn read_bits<const N: usize>(address: *const u32, bits: &[u32; N]) -> [bool; N] {
+    let current = read(address);
+
+    bits.map(|bit| current & (1 << bit) != 0)
+}
+pub unsafe fn write_bits<const N: usize>(address: *mut u32, bits: &[u32; N], values: [bool; N]) {
+    update(address, &|current| {
+        bits.iter().zip(values).fold(current, |result, (bit, set)| {
+            if set {
+                result | (1 << bit)
+            } else {
+                result & !(1 << bit)
+            }
+        })
+    })
+}
+
+pub unsafe fn set_bits(address: *mut u32, bits: &[u32]) {
+    update(address, &|current| {
+        bits.iter().fold(current, |result, bit| result | (1 << bit))
+    })
+}
+pub unsafe fn clear_bits(address: *mut u32, bits: &[u32]) {
+    update(address, &|current| {
+        bits.iter()
+            .fold(current, |result, bit| result & !(1 << bit))
+    })
+}
+pub unsafe fn toggle_bits(address: *mut u32, bits: &[u32]) {
+    update(address, &|current| {
+        bits.iter().fold(current, |result, bit| result ^ (1 << bit))
+    })
+}
+
+/// Atomically set one bit through its bit-band alias
+/// (falling back to a read-modify-write if `address` can't be bit-banded)
+pub unsafe fn set_bit_atomic(address: *mut u32, bit: u32) {
+    match bit_band::alias(address, bit) {
+        Some(alias) => write(alias, 1),
+        None => set_bits(address, &[bit]),
+    }
+}
+/// Atomically clear one bit through its bit-band alias
+/// (falling back to a read-modify-write if `address` can't be bit-banded)
+pub unsafe fn clear_bit_atomic(address: *mut u32, bit: u32) {
+    match bit_band::alias(address, bit) {
+        Some(alias) => write(alias, 0),
+        None => clear_bits(address, &[bit]),
+    }
+}
+/// Read one bit through its bit-band alias
+/// (falling back to reading the whole word if `address` can't be bit-banded)
+pub unsafe fn read_bit_atomic(address: *const u32, bit: u32) -> bool {
+    match bit_band::alias(address, bit) {
+        Some(alias) => read(alias) & 1 != 0,
+        None => {
+            let [value] = read_bits(address, &[bit]);
+            value
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simulated::{self, Access};
+    use super::*;
+
+    const REGISTER: *mut u32 = 0x4000_4400 as *mut u32;
+
+    #[test]
+    fn bit_helpers_read_modify_write() {
+        simulated::with(|memory| memory.preset(REGISTER, 0b1001));
+
+        unsafe {
+            set_bits(REGISTER, &[1, 2]);
+            clear_bits(REGISTER, &[0]);
+            toggle_bits(REGISTER, &[3, 4]);
+            write_bits(REGISTER, &[4, 5], [false, true]);
+        }
+
+        simulated::with(|memory| {
+            assert_eq!(memory.value(REGISTER), 0b10_0110);
+            assert_eq!(
+                memory.writes(),
+                [
+                    (REGISTER, 0b1111),
+                    (REGISTER, 0b1110),
+                    (REGISTER, 0b1_0110),
+                    (REGISTER, 0b10_0110),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn read_bits_only_reads() {
+        simulated::with(|memory| memory.preset(REGISTER, 0b0100));
+
+        let bits = unsafe { read_bits(REGISTER, &[2, 3]) };
+
+        assert_eq!(bits, [true, false]);
+        simulated::with(|memory| {
+            assert_eq!(memory.accesses(), [Access::Read(REGISTER, 0b0100)]);
+        });
+    }
+
+    #[test]
+    fn atomic_bits_only_write_the_alias() {
+        simulated::with(|memory| memory.preset(REGISTER, 0b1001));
+
+        unsafe {
+            set_bit_atomic(REGISTER, 1);
+            clear_bit_atomic(REGISTER, 0);
+        }
+        let set = unsafe { read_bit_atomic(REGISTER, 3) };
+
+        assert!(set);
+        let alias = |bit| bit_band::alias(REGISTER, bit).unwrap();
+        simulated::with(|memory| {
+            assert_eq!(memory.value(REGISTER), 0b1010);
+            assert_eq!(
+                memory.accesses(),
+                [
+                    Access::Write(alias(1), 1),
+                    Access::Write(alias(0), 0),
+                    Access::Read(alias(3), 1),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn atomic_bits_fall_back_outside_the_bit_band_regions() {
+        let register = 0xE000_E100 as *mut u32;
+
+        unsafe { set_bit_atomic(register, 4) };
+
+        simulated::with(|memory| {
+            assert_eq!(memory.value(register), 0b1_0000);
+            assert_eq!(
+                memory.accesses(),
+                [Access::Read(register, 0), Access::Write(register, 0b1_0000)]
+            );
+        });
+    }
+
+    #[test]
+    fn update_critical_disables_interrupts_around_the_read_and_write() {
+        unsafe {
+            update_critical(REGISTER, &|current| {
+                assert!(interrupts::disabled());
+                current | 1
+            });
+        }
+
+        assert!(!interrupts::disabled());
+        simulated::with(|memory| assert_eq!(memory.value(REGISTER), 1));
+    }
+}
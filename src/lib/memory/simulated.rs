@@ -0,0 +1,105 @@
+//! A simulated address space for running drivers on the host
+//!
+//! Each test thread gets its own, starting with every register at 0.
+//! Tests can preset registers (such as status flags the hardware would set)
+//! and afterwards assert on exactly which registers were touched and in what order.
+//! Accesses through a bit-band alias are recorded at the alias, and act on the word it stands for
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use super::{bit_band, Backend};
+
+/// One access the driver code made
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read(*mut u32, u32),
+    Write(*mut u32, u32),
+}
+
+#[derive(Default)]
+pub struct Simulated {
+    values: RefCell<BTreeMap<*mut u32, u32>>,
+    accesses: RefCell<Vec<Access>>,
+}
+
+impl Simulated {
+    /// Set the value of a register without recording an access
+    pub fn preset(&self, address: *mut u32, value: u32) {
+        self.values.borrow_mut().insert(address, value);
+    }
+
+    /// The current value of a register without recording an access
+    pub fn value(&self, address: *mut u32) -> u32 {
+        self.values.borrow().get(&address).copied().unwrap_or(0)
+    }
+
+    /// Every access made so far, in order
+    pub fn accesses(&self) -> Vec<Access> {
+        self.accesses.borrow().clone()
+    }
+
+    /// Every write made so far, in order
+    pub fn writes(&self) -> Vec<(*mut u32, u32)> {
+        self.accesses
+            .borrow()
+            .iter()
+            .filter_map(|access| match *access {
+                Access::Write(address, value) => Some((address, value)),
+                Access::Read(..) => None,
+            })
+            .collect()
+    }
+
+    /// The registers written so far, in order (without repeating consecutive writes to the same register)
+    pub fn written_registers(&self) -> Vec<*mut u32> {
+        let mut registers: Vec<*mut u32> = self
+            .writes()
+            .into_iter()
+            .map(|(address, _)| address)
+            .collect();
+        registers.dedup();
+        registers
+    }
+
+    /// Forget the accesses made so far (but keep the register values)
+    pub fn clear_accesses(&self) {
+        self.accesses.borrow_mut().clear();
+    }
+}
+
+impl Backend for Simulated {
+    unsafe fn read(&self, address: *const u32) -> u32 {
+        let address = address as *mut u32;
+        let value = match bit_band::target(address) {
+            Some((word, bit)) => (self.value(word) >> bit) & 1,
+            None => self.value(address),
+        };
+        self.accesses
+            .borrow_mut()
+            .push(Access::Read(address, value));
+        value
+    }
+    unsafe fn write(&self, address: *mut u32, new: u32) {
+        match bit_band::target(address) {
+            Some((word, bit)) => {
+                let value = (self.value(word) & !(1 << bit)) | ((new & 1) << bit);
+                self.values.borrow_mut().insert(word, value);
+            }
+            None => {
+                self.values.borrow_mut().insert(address, new);
+            }
+        }
+        self.accesses.borrow_mut().push(Access::Write(address, new));
+    }
+}
+
+std::thread_local! {
+    static CURRENT: Simulated = Simulated::default();
+}
+
+/// Use this test thread's simulated address space
+pub fn with<R>(f: impl FnOnce(&Simulated) -> R) -> R {
+    CURRENT.with(f)
+}
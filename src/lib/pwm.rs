@@ -0,0 +1,428 @@
+//! Pulse width modulation (PWM)
+//!
+//! Each of the 2 PWM modules has 4 generators, and each generator drives 2 outputs (A and B) from one counter.
+//! The counter counts down from the load value, and every output here goes high when the counter is reloaded
+//! and low when the counter passes the output's compare value, so the compare value sets the duty cycle
+//!
+//! Page 1239 of data sheet (Initialization and Configuration)
+
+use crate::registers::pwm::{_0_CMPA, _0_CMPB, _0_CTL, _0_GENA, _0_GENB, _0_LOAD, ENABLE};
+use crate::registers::sysctl::{PRPWM, RCC, RCGCPWM};
+use crate::registers::{peripherals, Bits, Field, FieldValue, Register, RegisterSpec};
+use crate::Board;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Module {
+    Zero = 0,
+    One = 1,
+}
+
+/// How far apart the registers of consecutive generators are
+///
+/// Page 1240 of data sheet (PWM Register Map)
+const GENERATOR_STRIDE: u32 = 0x40;
+
+impl Module {
+    /// The starting point of memory addresses corresponding to this PWM module
+    ///
+    /// Modeled after page 1240 of data sheet (PWM Register Map)
+    const fn base(&self) -> u32 {
+        match self {
+            Module::Zero => peripherals::PWM0,
+            Module::One => peripherals::PWM1,
+        }
+    }
+
+    /// The output enable (ENABLE) register for this module
+    ///
+    /// Page 1247 of data sheet
+    pub(super) const fn output_enable(&self) -> Register<ENABLE> {
+        Register::new(self.base())
+    }
+
+    /// A generator's register (generator 0's, moved over to `generator`)
+    const fn generator_register<S: RegisterSpec>(&self, generator: u32) -> Register<S> {
+        Register::at(self.base() + S::OFFSET + GENERATOR_STRIDE * generator)
+    }
+
+    /// The control (CTL) register for one of this module's generators
+    ///
+    /// Page 1266 of data sheet
+    pub(super) const fn generator_control(&self, generator: u32) -> Register<_0_CTL> {
+        self.generator_register(generator)
+    }
+
+    /// The load (LOAD) register for one of this module's generators
+    ///
+    /// Page 1278 of data sheet
+    pub(super) const fn load(&self, generator: u32) -> Register<_0_LOAD> {
+        self.generator_register(generator)
+    }
+
+    /// The compare A (CMPA) register for one of this module's generators
+    ///
+    /// Page 1280 of data sheet
+    pub(super) const fn compare_a(&self, generator: u32) -> Register<_0_CMPA> {
+        self.generator_register(generator)
+    }
+
+    /// The compare B (CMPB) register for one of this module's generators
+    ///
+    /// Page 1281 of data sheet
+    pub(super) const fn compare_b(&self, generator: u32) -> Register<_0_CMPB> {
+        self.generator_register(generator)
+    }
+
+    /// The generator A control (GENA) register for one of this module's generators
+    ///
+    /// Page 1282 of data sheet
+    pub(super) const fn generator_a(&self, generator: u32) -> Register<_0_GENA> {
+        self.generator_register(generator)
+    }
+
+    /// The generator B control (GENB) register for one of this module's generators
+    ///
+    /// Page 1285 of data sheet
+    pub(super) const fn generator_b(&self, generator: u32) -> Register<_0_GENB> {
+        self.generator_register(generator)
+    }
+}
+
+impl Module {
+    /// The corresponding field for this module in the system's PWM Run mode clock gating control (RCGCPWM) register
+    const fn run_mode_clock_gating_control_field(&self) -> Field<RCGCPWM, 1> {
+        match self {
+            Module::Zero => RCGCPWM::R0,
+            Module::One => RCGCPWM::R1,
+        }
+    }
+
+    /// The corresponding field for this module in the system's PWM peripheral ready (PRPWM) register
+    const fn peripheral_ready_field(&self) -> Field<PRPWM, 1> {
+        match self {
+            Module::Zero => PRPWM::R0,
+            Module::One => PRPWM::R1,
+        }
+    }
+}
+
+/// One of the 8 outputs of a module (MnPWM0 to MnPWM7)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Output {
+    module: Module,
+    number: u32,
+}
+
+impl Output {
+    /// Output MnPWM`number` of `module`
+    ///
+    /// Panics if `number` isn't 0 through 7
+    pub const fn new(module: Module, number: u32) -> Self {
+        assert!(number < 8, "PWM modules only have outputs 0 through 7");
+
+        Output { module, number }
+    }
+
+    pub const fn module(&self) -> Module {
+        self.module
+    }
+    pub const fn number(&self) -> u32 {
+        self.number
+    }
+
+    /// Outputs 2n and 2n + 1 are A and B of generator n
+    const fn generator(&self) -> u32 {
+        self.number / 2
+    }
+    /// The other output of the same generator, which shares its counter (and so its frequency)
+    pub(crate) const fn sibling(&self) -> Output {
+        Output {
+            module: self.module,
+            number: self.number ^ 1,
+        }
+    }
+    const fn is_b(&self) -> bool {
+        self.number % 2 == 1
+    }
+}
+
+pub struct OutputOptions {
+    pub frequency_hz: u32,
+}
+
+/// Why an output couldn't be set up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetupError {
+    /// The output is already set up
+    AlreadySetUp,
+    /// The frequency is 0, faster than the PWM clock, or too slow for the 16-bit counter
+    UnreachableFrequency,
+    /// The other output of the same generator is already running at this frequency (in Hz), and the two share a counter
+    GeneratorInUse(u32),
+}
+
+/// Page 1282 of data sheet (ACTLOAD, ACTCMPAD, ... field encodings)
+#[derive(Clone, Copy)]
+enum Action {
+    Nothing,
+    DriveLow,
+    DriveHigh,
+}
+
+impl FieldValue<2> for Action {
+    fn into_bits(self) -> u32 {
+        match self {
+            Action::Nothing => 0x0,
+            Action::DriveLow => 0x2,
+            Action::DriveHigh => 0x3,
+        }
+    }
+    fn from_bits(bits: u32) -> Self {
+        match bits {
+            0x2 => Action::DriveLow,
+            0x3 => Action::DriveHigh,
+            _ => Action::Nothing,
+        }
+    }
+}
+
+/// The PWM clock is the system clock divided by 2 (page 254 of data sheet, PWMDIV field encodings),
+/// which keeps a whole period of anything above about 610 Hz within the 16-bit counter even at 80 MHz
+const PWM_CLOCK_DIVISOR: u32 = 2;
+const PWM_DIVISOR_2: u32 = 0x0;
+
+/// A setup PWM output, which starts out always low
+pub struct UsableOutput {
+    output: Output,
+    /// What the counter counts down from
+    load: u32,
+}
+
+impl UsableOutput {
+    pub fn output(&self) -> Output {
+        self.output
+    }
+
+    /// Keep the output high for `duty` 255ths of each period
+    pub fn set_duty(&mut self, duty: u8) {
+        let module = self.output.module;
+
+        // The output would still be high for a cycle of every period with a compare value
+        // that's as high as the load value, so it's simply disabled (which holds it low) instead
+        if duty == 0 {
+            module.output_enable().clear_bit_atomic(self.output.number);
+            return;
+        }
+
+        let compare = self.load - self.load * duty as u32 / 255;
+        if self.output.is_b() {
+            module.compare_b(self.output.generator()).write(compare);
+        } else {
+            module.compare_a(self.output.generator()).write(compare);
+        }
+
+        module.output_enable().set_bit_atomic(self.output.number);
+    }
+}
+
+/// Page 1239 of data sheet (Initialization and Configuration)
+///
+/// `generator_frequency_hz` is the frequency the output's generator is already running at for its other output, if it is.
+/// The generator is left running then, so the other output isn't disturbed
+pub fn setup_output(
+    board: Board,
+    output: Output,
+    options: OutputOptions,
+    system_clock_hz: u32,
+    generator_frequency_hz: Option<u32>,
+) -> Result<UsableOutput, SetupError> {
+    let module = output.module;
+    let generator = output.generator();
+
+    if let Some(frequency_hz) = generator_frequency_hz {
+        if frequency_hz != options.frequency_hz {
+            return Err(SetupError::GeneratorInUse(frequency_hz));
+        }
+    }
+
+    // Worked out before anything is changed, so nothing is if it can't be reached
+    let pwm_clock_hz = system_clock_hz / PWM_CLOCK_DIVISOR;
+    if options.frequency_hz == 0 || options.frequency_hz > pwm_clock_hz {
+        return Err(SetupError::UnreachableFrequency);
+    }
+    let load = pwm_clock_hz / options.frequency_hz - 1;
+    if load > 0xFFFF {
+        return Err(SetupError::UnreachableFrequency);
+    }
+
+    // Activate the associated peripheral and wait for it to be ready to use
+    board
+        .pwm_run_mode_clock_gating_control()
+        .modify_atomic(module.run_mode_clock_gating_control_field(), true);
+    while !board
+        .pwm_peripheral_ready()
+        .read_field::<1, bool>(module.peripheral_ready_field())
+    {}
+
+    // Both modules share the PWM clock
+    let rcc = board.run_mode_clock_configuration();
+    rcc.modify(RCC::PWMDIV, Bits::<3>::new(PWM_DIVISOR_2));
+    rcc.modify(RCC::USEPWMDIV, true);
+
+    if generator_frequency_hz.is_none() {
+        // Stop the generator (leaving it counting down) while it's being set up
+        module.generator_control(generator).write(0);
+        module
+            .load(generator)
+            .modify(_0_LOAD::LOAD, Bits::<16>::new(load));
+    }
+
+    // High from the start of each period until the counter passes the compare value on its way down
+    if output.is_b() {
+        let generator_b = module.generator_b(generator);
+        generator_b.write(0);
+        generator_b.modify(_0_GENB::ACTLOAD, Action::DriveHigh);
+        generator_b.modify(_0_GENB::ACTCMPBD, Action::DriveLow);
+    } else {
+        let generator_a = module.generator_a(generator);
+        generator_a.write(0);
+        generator_a.modify(_0_GENA::ACTLOAD, Action::DriveHigh);
+        generator_a.modify(_0_GENA::ACTCMPAD, Action::DriveLow);
+    }
+
+    if generator_frequency_hz.is_none() {
+        module
+            .generator_control(generator)
+            .modify(_0_CTL::ENABLE, true);
+    }
+
+    let mut usable_output = UsableOutput { output, load };
+    usable_output.set_duty(0);
+    Ok(usable_output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::simulated;
+
+    #[test]
+    fn addresses_match_the_data_sheet() {
+        let modules = [(Module::Zero, 0x4002_8000), (Module::One, 0x4002_9000)];
+
+        for (module, base) in modules {
+            assert_eq!(module.output_enable().address() as u32, base + 0x008);
+            assert_eq!(module.generator_control(0).address() as u32, base + 0x040);
+            assert_eq!(module.generator_control(3).address() as u32, base + 0x100);
+            assert_eq!(module.load(2).address() as u32, base + 0x0D0);
+            assert_eq!(module.compare_a(3).address() as u32, base + 0x118);
+            assert_eq!(module.compare_b(3).address() as u32, base + 0x11C);
+            assert_eq!(module.generator_a(1).address() as u32, base + 0x0A0);
+            assert_eq!(module.generator_b(3).address() as u32, base + 0x124);
+        }
+
+        assert_eq!(
+            Board.pwm_run_mode_clock_gating_control().address() as u32,
+            0x400F_E640
+        );
+        assert_eq!(Board.pwm_peripheral_ready().address() as u32, 0x400F_EA40);
+    }
+
+    #[test]
+    fn duty_sets_how_long_each_period_is_high() {
+        simulated::with(|memory| {
+            memory.preset(Board.pwm_peripheral_ready().address(), 0b10);
+        });
+
+        let output = Output::new(Module::One, 7);
+        let mut usable_output = setup_output(
+            Board,
+            output,
+            OutputOptions { frequency_hz: 1000 },
+            16_000_000,
+            None,
+        )
+        .unwrap();
+        let enable = Module::One.output_enable().address();
+
+        simulated::with(|memory| {
+            assert_eq!(memory.value(Module::One.load(3).address()), 7999);
+            assert_eq!(
+                memory.value(Module::One.generator_b(3).address()),
+                (0x3 << 2) | (0x2 << 10)
+            );
+            assert_eq!(memory.value(Module::One.generator_control(3).address()), 1);
+            assert_eq!(memory.value(enable), 0);
+        });
+
+        usable_output.set_duty(255);
+        simulated::with(|memory| {
+            assert_eq!(memory.value(Module::One.compare_b(3).address()), 0);
+            assert_eq!(memory.value(enable), 1 << 7);
+        });
+
+        usable_output.set_duty(51);
+        simulated::with(|memory| {
+            assert_eq!(memory.value(Module::One.compare_b(3).address()), 6400);
+        });
+
+        usable_output.set_duty(0);
+        simulated::with(|memory| assert_eq!(memory.value(enable), 0));
+    }
+
+    #[test]
+    fn generators_are_shared_at_one_frequency() {
+        simulated::with(|memory| {
+            memory.preset(Board.pwm_peripheral_ready().address(), 0b1);
+        });
+
+        let setup = |number, frequency_hz, generator_frequency_hz| {
+            setup_output(
+                Board,
+                Output::new(Module::Zero, number),
+                OutputOptions { frequency_hz },
+                16_000_000,
+                generator_frequency_hz,
+            )
+        };
+
+        assert!(setup(2, 1000, None).is_ok());
+        simulated::with(|memory| memory.clear_accesses());
+
+        // The sibling can't retime the generator
+        assert_eq!(
+            setup(3, 2000, Some(1000)).err(),
+            Some(SetupError::GeneratorInUse(1000))
+        );
+        simulated::with(|memory| assert!(memory.writes().is_empty()));
+
+        // At the same frequency, it joins in without stopping the generator
+        assert!(setup(3, 1000, Some(1000)).is_ok());
+        simulated::with(|memory| {
+            let written = memory.written_registers();
+            assert!(!written.contains(&Module::Zero.generator_control(1).address()));
+            assert!(!written.contains(&Module::Zero.load(1).address()));
+            assert_eq!(memory.value(Module::Zero.load(1).address()), 7999);
+        });
+    }
+
+    #[test]
+    fn unreachable_frequencies_are_refused() {
+        let setup = |frequency_hz| {
+            setup_output(
+                Board,
+                Output::new(Module::Zero, 0),
+                OutputOptions { frequency_hz },
+                16_000_000,
+                None,
+            )
+            .err()
+        };
+
+        assert_eq!(setup(0), Some(SetupError::UnreachableFrequency));
+        assert_eq!(setup(8_000_001), Some(SetupError::UnreachableFrequency));
+        // 8 MHz over 65536 is just over 122 Hz
+        assert_eq!(setup(122), Some(SetupError::UnreachableFrequency));
+        simulated::with(|memory| assert!(memory.writes().is_empty()));
+    }
+}
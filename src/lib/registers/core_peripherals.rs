@@ -0,0 +1,107 @@
+//! Cortex-M4 core peripheral registers
+//!
+//! The core peripherals (SysTick, the NVIC, and the system control block) are part of the processor
+//! rather than the microcontroller, so they aren't in the SVD file and are written out here by hand
+//!
+//! Page 134 of data sheet (Core Peripheral Register Map)
+
+use super::{Field, ReadWrite, RegisterSpec};
+
+/// Where the core peripherals start (every offset in the data sheet's map is from here)
+pub const BASE: u32 = 0xE000_E000;
+
+/// SysTick Control and Status Register
+///
+/// Page 138 of data sheet
+pub struct STCTRL;
+impl RegisterSpec for STCTRL {
+    const OFFSET: u32 = 0x010;
+    const RESET: u32 = 0x0000_0004;
+    type Access = ReadWrite;
+}
+impl STCTRL {
+    /// Enable the counter
+    pub const ENABLE: Field<STCTRL, 1> = Field::new(0);
+    /// Raise the SysTick exception when the counter reaches 0
+    pub const INTEN: Field<STCTRL, 1> = Field::new(1);
+    /// Count the system clock (instead of PIOSC divided by 4)
+    pub const CLK_SRC: Field<STCTRL, 1> = Field::new(2);
+}
+
+/// SysTick Reload Value Register
+///
+/// Page 140 of data sheet
+pub struct STRELOAD;
+impl RegisterSpec for STRELOAD {
+    const OFFSET: u32 = 0x014;
+    const RESET: u32 = 0x0000_0000;
+    type Access = ReadWrite;
+}
+impl STRELOAD {
+    /// The value loaded into the counter when it reaches 0
+    pub const RELOAD: Field<STRELOAD, 24> = Field::new(0);
+}
+
+/// SysTick Current Value Register
+///
+/// Page 141 of data sheet
+pub struct STCURRENT;
+impl RegisterSpec for STCURRENT {
+    const OFFSET: u32 = 0x018;
+    const RESET: u32 = 0x0000_0000;
+    type Access = ReadWrite;
+}
+impl STCURRENT {
+    /// The current value of the counter (writing any value clears it to 0)
+    pub const CURRENT: Field<STCURRENT, 24> = Field::new(0);
+}
+
+/// Interrupt Control and State
+///
+/// Page 160 of data sheet
+pub struct INTCTRL;
+impl RegisterSpec for INTCTRL {
+    const OFFSET: u32 = 0xD04;
+    const RESET: u32 = 0x0000_0000;
+    type Access = ReadWrite;
+}
+impl INTCTRL {
+    /// Read: the SysTick exception is pending. Write 1: make it pending
+    pub const PENDSTSET: Field<INTCTRL, 1> = Field::new(26);
+}
+
+/// Interrupt 0-31 Set Enable (EN0), followed by EN1 to EN4 for the rest (32 interrupts per register)
+///
+/// Writing a 1 enables that interrupt; writing a 0 has no effect
+///
+/// Page 142 of data sheet
+pub struct EN;
+impl RegisterSpec for EN {
+    const OFFSET: u32 = 0x100;
+    const RESET: u32 = 0x0000_0000;
+    type Access = ReadWrite;
+}
+
+/// Interrupt 0-31 Clear Enable (DIS0), followed by DIS1 to DIS4 for the rest (32 interrupts per register)
+///
+/// Writing a 1 disables that interrupt; writing a 0 has no effect
+///
+/// Page 146 of data sheet
+pub struct DIS;
+impl RegisterSpec for DIS {
+    const OFFSET: u32 = 0x180;
+    const RESET: u32 = 0x0000_0000;
+    type Access = ReadWrite;
+}
+
+/// Interrupt 0-3 Priority (PRI0), followed by PRI1 to PRI34 for the rest (4 interrupts per register)
+///
+/// Each interrupt gets a byte, of which only the top 3 bits are implemented
+///
+/// Page 152 of data sheet
+pub struct PRI;
+impl RegisterSpec for PRI {
+    const OFFSET: u32 = 0x400;
+    const RESET: u32 = 0x0000_0000;
+    type Access = ReadWrite;
+}
@@ -0,0 +1,15 @@
+//! GPIO registers
+//!
+//! Page 660 of data sheet (GPIO Register Map).
+//! Most GPIO registers hold one bit per pin, so they have no fields of their own
+
+pub use super::generated::gpio_porta::*;
+use super::Field;
+use crate::Pin;
+
+impl PCTL {
+    /// The port mux control (PMCn) field selecting the digital function of `pin`
+    pub const fn pmc(pin: Pin) -> Field<PCTL, 4> {
+        Field::new(pin as u32 * 4)
+    }
+}
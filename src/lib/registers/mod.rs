@@ -0,0 +1,295 @@
+//! Typed registers
+//!
+//! Each register is described by a type implementing [`RegisterSpec`] (its offset from the
+//! peripheral's base address, its reset value, and whether it can be read and/or written),
+//! and each field of it by a [`Field`] that knows its register and its width.
+//! Drivers get a [`Register`] from the peripheral and read or modify whole fields with values
+//! of the matching width, so a 2-bit field can only be written with a 2-bit value
+//!
+//! The register and field definitions are generated by `build.rs` from the vendored SVD file
+//! (`svd/TM4C123GH6PM.svd`), and re-exported by the per-peripheral modules below.
+//! The Cortex-M4's own registers aren't in it, so [`core_peripherals`] is written by hand
+
+// Registers and fields are named exactly as they are in the data sheet
+#![allow(clippy::upper_case_acronyms)]
+
+use core::marker::PhantomData;
+
+use crate::memory;
+
+pub mod core_peripherals;
+pub mod gpio;
+pub mod pwm;
+pub mod sysctl;
+pub mod uart;
+
+/// Everything in the SVD file, whether or not a driver uses it yet
+#[allow(dead_code, non_camel_case_types)]
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/tm4c123gh6pm.rs"));
+}
+
+pub use generated::{interrupts, peripherals};
+
+/// The register can only be read
+pub struct ReadOnly;
+/// The register can only be written
+pub struct WriteOnly;
+/// The register can be read and written
+pub struct ReadWrite;
+
+pub trait Readable {}
+impl Readable for ReadOnly {}
+impl Readable for ReadWrite {}
+
+pub trait Writable {}
+impl Writable for WriteOnly {}
+impl Writable for ReadWrite {}
+
+/// Describes a register
+pub trait RegisterSpec {
+    /// The offset of this register from the base address of its peripheral
+    const OFFSET: u32;
+    /// The value of this register after reset
+    #[allow(dead_code)]
+    const RESET: u32;
+    /// [`ReadOnly`], [`WriteOnly`], or [`ReadWrite`]
+    type Access;
+}
+
+/// A `WIDTH`-bit field of the register `S`
+pub struct Field<S, const WIDTH: u32> {
+    offset: u32,
+    _spec: PhantomData<S>,
+}
+
+impl<S, const WIDTH: u32> Clone for Field<S, WIDTH> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<S, const WIDTH: u32> Copy for Field<S, WIDTH> {}
+
+impl<S, const WIDTH: u32> Field<S, WIDTH> {
+    /// A field starting at bit `offset`
+    pub const fn new(offset: u32) -> Self {
+        assert!(offset + WIDTH <= 32);
+
+        Field {
+            offset,
+            _spec: PhantomData,
+        }
+    }
+
+    /// The bits of the register that this field occupies
+    pub const fn mask(&self) -> u32 {
+        (u32::MAX >> (32 - WIDTH)) << self.offset
+    }
+}
+
+/// A value that fits in a `WIDTH`-bit field
+pub trait FieldValue<const WIDTH: u32> {
+    fn into_bits(self) -> u32;
+    fn from_bits(bits: u32) -> Self;
+}
+
+impl FieldValue<1> for bool {
+    fn into_bits(self) -> u32 {
+        self as u32
+    }
+    fn from_bits(bits: u32) -> Self {
+        bits != 0
+    }
+}
+
+/// A raw `WIDTH`-bit number for fields that don't have a more meaningful type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bits<const WIDTH: u32>(u32);
+
+impl<const WIDTH: u32> Bits<WIDTH> {
+    /// Panics (at compile time when used in a constant) if `value` doesn't fit in `WIDTH` bits
+    pub const fn new(value: u32) -> Self {
+        assert!(WIDTH == 32 || value >> WIDTH == 0);
+        Bits(value)
+    }
+
+    pub const fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+impl<const WIDTH: u32> FieldValue<WIDTH> for Bits<WIDTH> {
+    fn into_bits(self) -> u32 {
+        self.0
+    }
+    fn from_bits(bits: u32) -> Self {
+        Bits(bits)
+    }
+}
+
+/// A register of type `S` at a particular address
+pub struct Register<S> {
+    address: u32,
+    _spec: PhantomData<S>,
+}
+
+impl<S> Clone for Register<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<S> Copy for Register<S> {}
+
+impl<S: RegisterSpec> Register<S> {
+    /// The register `S` of the peripheral starting at `base`
+    pub const fn new(base: u32) -> Self {
+        Self::at(base + S::OFFSET)
+    }
+
+    /// The register `S` at exactly `address` (for registers that have more than one alias)
+    pub const fn at(address: u32) -> Self {
+        Register {
+            address,
+            _spec: PhantomData,
+        }
+    }
+
+    pub const fn address(&self) -> *mut u32 {
+        self.address as *mut u32
+    }
+}
+
+impl<S: RegisterSpec> Register<S>
+where
+    S::Access: Readable,
+{
+    pub fn read(&self) -> u32 {
+        unsafe { memory::read(self.address()) }
+    }
+
+    pub fn read_field<const WIDTH: u32, V: FieldValue<WIDTH>>(&self, field: Field<S, WIDTH>) -> V {
+        V::from_bits((self.read() & field.mask()) >> field.offset)
+    }
+
+    pub fn read_bits<const N: usize>(&self, bits: &[u32; N]) -> [bool; N] {
+        unsafe { memory::read_bits(self.address(), bits) }
+    }
+
+    /// Read one bit through its bit-band alias
+    pub fn read_bit_atomic(&self, bit: u32) -> bool {
+        unsafe { memory::read_bit_atomic(self.address(), bit) }
+    }
+}
+
+impl<S: RegisterSpec> Register<S>
+where
+    S::Access: Writable,
+{
+    pub fn write(&self, value: u32) {
+        unsafe { memory::write(self.address(), value) }
+    }
+}
+
+impl<S: RegisterSpec> Register<S>
+where
+    S::Access: Readable + Writable,
+{
+    pub fn update(&self, updater: &dyn Fn(u32) -> u32) {
+        unsafe { memory::update(self.address(), updater) }
+    }
+
+    /// Like [`Register::update`], but with interrupts disabled between the read and the write
+    pub fn update_critical(&self, updater: &dyn Fn(u32) -> u32) {
+        unsafe { memory::update_critical(self.address(), updater) }
+    }
+
+    /// Read-modify-write one field, leaving the rest of the register as it was
+    pub fn modify<const WIDTH: u32, V: FieldValue<WIDTH>>(&self, field: Field<S, WIDTH>, value: V) {
+        let bits = (value.into_bits() << field.offset) & field.mask();
+        self.update(&|current| (current & !field.mask()) | bits);
+    }
+
+    pub fn write_bits<const N: usize>(&self, bits: &[u32; N], values: [bool; N]) {
+        unsafe { memory::write_bits(self.address(), bits, values) }
+    }
+    pub fn set_bits(&self, bits: &[u32]) {
+        unsafe { memory::set_bits(self.address(), bits) }
+    }
+    pub fn clear_bits(&self, bits: &[u32]) {
+        unsafe { memory::clear_bits(self.address(), bits) }
+    }
+    pub fn toggle_bits(&self, bits: &[u32]) {
+        unsafe { memory::toggle_bits(self.address(), bits) }
+    }
+
+    /// Set one bit without a read-modify-write that an interrupt could come in the middle of
+    pub fn set_bit_atomic(&self, bit: u32) {
+        unsafe { memory::set_bit_atomic(self.address(), bit) }
+    }
+    /// Clear one bit without a read-modify-write that an interrupt could come in the middle of
+    pub fn clear_bit_atomic(&self, bit: u32) {
+        unsafe { memory::clear_bit_atomic(self.address(), bit) }
+    }
+    /// Write a 1-bit field without a read-modify-write that an interrupt could come in the middle of
+    pub fn modify_atomic(&self, field: Field<S, 1>, value: bool) {
+        if value {
+            self.set_bit_atomic(field.offset);
+        } else {
+            self.clear_bit_atomic(field.offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::uart::{FR, LCRH};
+    use super::*;
+    use crate::memory::simulated;
+
+    const UART0: u32 = 0x4000_C000;
+
+    #[test]
+    fn modify_only_touches_the_field() {
+        let line_control = Register::<LCRH>::new(UART0);
+        simulated::with(|memory| memory.preset(line_control.address(), 0b1111_1111));
+
+        line_control.modify(LCRH::WLEN, Bits::<2>::new(0b01));
+
+        assert_eq!(line_control.address() as u32, UART0 + 0x02C);
+        simulated::with(|memory| {
+            assert_eq!(memory.value(line_control.address()), 0b1011_1111);
+        });
+    }
+
+    #[test]
+    fn read_field_shifts_down() {
+        let flag = Register::<FR>::new(UART0);
+        simulated::with(|memory| memory.preset(flag.address(), 1 << 5));
+
+        let transmit_fifo_full: bool = flag.read_field(FR::TXFF);
+        let receive_fifo_empty: bool = flag.read_field(FR::RXFE);
+
+        assert!(transmit_fifo_full);
+        assert!(!receive_fifo_empty);
+    }
+
+    #[test]
+    fn reset_values_match_the_data_sheet() {
+        // Pages 660, 904, and 231 of data sheet (register maps)
+        assert_eq!(gpio::LOCK::RESET, 0x0000_0001);
+        assert_eq!(gpio::DR2R::RESET, 0x0000_00FF);
+        assert_eq!(uart::LCRH::RESET, 0x0000_0000);
+        assert_eq!(uart::FR::RESET, 0x0000_0090);
+        assert_eq!(uart::CTL::RESET, 0x0000_0300);
+        assert_eq!(uart::IFLS::RESET, 0x0000_0012);
+        assert_eq!(sysctl::RCC::RESET, 0x078E_3AD1);
+        assert_eq!(sysctl::RCC2::RESET, 0x07C0_6810);
+        assert_eq!(core_peripherals::STCTRL::RESET, 0x0000_0004);
+    }
+
+    #[test]
+    #[should_panic]
+    fn bits_must_fit_the_width() {
+        Bits::<2>::new(0b100);
+    }
+}
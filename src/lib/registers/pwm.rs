@@ -0,0 +1,7 @@
+//! PWM registers
+//!
+//! Page 1240 of data sheet (PWM Register Map).
+//! The 4 generators of a module have identical registers 0x40 apart, so drivers use generator 0's
+//! (`_0_CTL`, `_0_LOAD`, ...) for all of them, offset by the generator's number
+
+pub use super::generated::pwm0::*;
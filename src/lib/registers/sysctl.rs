@@ -0,0 +1,5 @@
+//! System control registers
+//!
+//! Page 231 of data sheet (System Control Register Map)
+
+pub use super::generated::sysctl::*;
@@ -0,0 +1,5 @@
+//! UART registers
+//!
+//! Page 904 of data sheet (UART Register Map)
+
+pub use super::generated::uart0::*;
@@ -0,0 +1,272 @@
+//! An RGB LED with a PWM output (or just a pin) for each of its channels
+//!
+//! With hardware PWM every channel can be any of 256 levels. Pins without PWM can still show
+//! those levels by switching on and off fast enough while a color is held ([`RgbLed::hold`]);
+//! when a color is just set, each channel is either fully on or off
+
+use crate::time::{self, Duration, Stopwatch};
+use crate::{UsablePWMOutput, WritablePin, WritablePins};
+
+/// A color as 8-bit red, green, and blue levels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::new(0, 0, 0);
+    pub const WHITE: Color = Color::new(255, 255, 255);
+
+    pub const RED: Color = Color::new(255, 0, 0);
+    pub const YELLOW: Color = Color::new(255, 255, 0);
+    pub const GREEN: Color = Color::new(0, 255, 0);
+    pub const CYAN: Color = Color::new(0, 255, 255);
+    pub const BLUE: Color = Color::new(0, 0, 255);
+    pub const MAGENTA: Color = Color::new(255, 0, 255);
+
+    pub const RAINBOW: [Color; 6] = [
+        Color::RED,
+        Color::YELLOW,
+        Color::GREEN,
+        Color::CYAN,
+        Color::BLUE,
+        Color::MAGENTA,
+    ];
+
+    pub const fn new(red: u8, green: u8, blue: u8) -> Self {
+        Color { red, green, blue }
+    }
+
+    /// A color written as 0xRRGGBB (the top byte is ignored)
+    pub const fn from_rgb(rgb: u32) -> Self {
+        Color::new((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+    }
+
+    pub const fn to_rgb(self) -> u32 {
+        (self.red as u32) << 16 | (self.green as u32) << 8 | self.blue as u32
+    }
+
+    /// The same color at `brightness` 255ths of the brightness
+    pub const fn scaled(self, brightness: u8) -> Self {
+        const fn scale(level: u8, brightness: u8) -> u8 {
+            (level as u32 * brightness as u32 / 255) as u8
+        }
+
+        Color::new(
+            scale(self.red, brightness),
+            scale(self.green, brightness),
+            scale(self.blue, brightness),
+        )
+    }
+
+    /// The color `step` `steps`ths of the way from this color to `to`
+    pub fn mix(self, to: Color, step: u32, steps: u32) -> Self {
+        let mix = |from: u8, to: u8| {
+            (from as i32 + (to as i32 - from as i32) * step as i32 / steps as i32) as u8
+        };
+
+        Color::new(
+            mix(self.red, to.red),
+            mix(self.green, to.green),
+            mix(self.blue, to.blue),
+        )
+    }
+
+    fn levels(self) -> [u8; 3] {
+        [self.red, self.green, self.blue]
+    }
+}
+
+/// One step of a pattern
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Show the color for the duration
+    Hold(Color, Duration),
+    /// Fade from whatever is showing to the color over the duration
+    Fade(Color, Duration),
+}
+
+enum Channels {
+    Pwm {
+        outputs: [UsablePWMOutput; 3],
+        /// The pins the outputs come out of, which are kept so nothing else can set them up
+        _pins: [WritablePin; 3],
+    },
+    Software(WritablePins<3>),
+}
+
+/// How long each period of software PWM is
+const SOFTWARE_PERIOD_MICROS: u64 = 2_000;
+
+/// How often the color changes while fading
+const FADE_STEP_MILLIS: u64 = 10;
+
+pub struct RgbLed {
+    channels: Channels,
+    color: Color,
+    brightness: u8,
+}
+
+impl RgbLed {
+    /// An RGB LED driven by hardware PWM, with its red, green, and blue outputs (in that order)
+    /// and the pins they're set up on
+    pub fn pwm(outputs: [UsablePWMOutput; 3], pins: [WritablePin; 3]) -> Self {
+        let mut rgb_led = RgbLed {
+            channels: Channels::Pwm {
+                outputs,
+                _pins: pins,
+            },
+            color: Color::BLACK,
+            brightness: 255,
+        };
+        rgb_led.set(Color::BLACK);
+        rgb_led
+    }
+
+    /// An RGB LED driven directly by its red, green, and blue pins (in that order)
+    pub fn software(pins: WritablePins<3>) -> Self {
+        let mut rgb_led = RgbLed {
+            channels: Channels::Software(pins),
+            color: Color::BLACK,
+            brightness: 255,
+        };
+        rgb_led.set(Color::BLACK);
+        rgb_led
+    }
+
+    /// The color last set (before brightness is applied)
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Scale every color shown from now on to `brightness` 255ths of its brightness
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness;
+        self.set(self.color);
+    }
+
+    /// Show `color` until it's changed
+    ///
+    /// Without hardware PWM, each channel is either on (at 128 and up) or off
+    pub fn set(&mut self, color: Color) {
+        self.color = color;
+        let levels = color.scaled(self.brightness).levels();
+
+        match &mut self.channels {
+            Channels::Pwm { outputs, .. } => {
+                for (output, level) in outputs.iter_mut().zip(levels) {
+                    output.set_duty(level);
+                }
+            }
+            Channels::Software(pins) => pins.write_all(levels.map(|level| level >= 128)),
+        }
+    }
+
+    /// Show `color` for `duration` (and leave it showing)
+    pub fn hold(&mut self, color: Color, duration: Duration) {
+        let Channels::Software(_) = self.channels else {
+            self.set(color);
+            time::delay(duration);
+            return;
+        };
+
+        self.color = color;
+        let levels = color.scaled(self.brightness).levels();
+
+        // Timed by reading SysTick's counter, so this works with interrupts disabled too (like in the panic handler)
+        let mut stopwatch = Stopwatch::start();
+        let period = Duration::from_micros(SOFTWARE_PERIOD_MICROS).ticks().max(1);
+
+        loop {
+            let elapsed = stopwatch.elapsed();
+            if elapsed >= duration {
+                break;
+            }
+
+            // How far through the current period it is, in 255ths
+            let phase = (elapsed.ticks() % period * 255 / period) as u8;
+
+            if let Channels::Software(pins) = &mut self.channels {
+                pins.write_all(levels.map(|level| level > phase));
+            }
+        }
+
+        self.set(color);
+    }
+
+    /// Fade from the color that's showing to `color` over `duration`
+    pub fn fade(&mut self, color: Color, duration: Duration) {
+        let from = self.color;
+        let step = Duration::from_millis(FADE_STEP_MILLIS);
+        let steps = (duration.ticks() / step.ticks().max(1)).max(1) as u32;
+
+        for i in 1..=steps {
+            self.hold(
+                from.mix(color, i, steps),
+                Duration::from_ticks(duration.ticks() / steps as u64),
+            );
+        }
+    }
+
+    /// Flash `color` `times` times, on for `on` then off for `off` each time
+    pub fn blink(&mut self, color: Color, on: Duration, off: Duration, times: u32) {
+        for _ in 0..times {
+            self.hold(color, on);
+            self.hold(Color::BLACK, off);
+        }
+    }
+
+    /// Go through `pattern` once
+    pub fn play(&mut self, pattern: &[Step]) {
+        for step in pattern {
+            match *step {
+                Step::Hold(color, duration) => self.hold(color, duration),
+                Step::Fade(color, duration) => self.fade(color, duration),
+            }
+        }
+    }
+
+    /// Go through `pattern` over and over again, forever
+    pub fn repeat(&mut self, pattern: &[Step]) -> ! {
+        loop {
+            self.play(pattern);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpio::pins::steal_writable_pins;
+    use crate::gpio::ports::Port;
+    use crate::memory::simulated;
+    use crate::{Pin, WritablePinOptions};
+
+    #[test]
+    fn colors_convert_scale_and_mix() {
+        assert_eq!(Color::from_rgb(0xFF8000), Color::new(255, 128, 0));
+        assert_eq!(Color::new(255, 128, 0).to_rgb(), 0xFF8000);
+        assert_eq!(Color::WHITE.scaled(51), Color::new(51, 51, 51));
+        assert_eq!(Color::RED.mix(Color::BLUE, 1, 4), Color::new(192, 0, 63));
+        assert_eq!(Color::RED.mix(Color::BLUE, 4, 4), Color::BLUE);
+    }
+
+    #[test]
+    fn software_channels_are_on_or_off_when_set() {
+        let pins = [Pin::One, Pin::Three, Pin::Two];
+        let mut rgb_led = RgbLed::software(
+            steal_writable_pins(Port::F, pins, WritablePinOptions::default()).unwrap(),
+        );
+        // The DATA alias for pins 1, 2, and 3
+        let data = 0x4002_5038 as *mut u32;
+
+        rgb_led.set(Color::new(200, 20, 128));
+        simulated::with(|memory| assert_eq!(memory.value(data), 0b0110));
+
+        rgb_led.set_brightness(127);
+        assert_eq!(rgb_led.color(), Color::new(200, 20, 128));
+        simulated::with(|memory| assert_eq!(memory.value(data), 0b0000));
+    }
+}
@@ -0,0 +1,304 @@
+//! Delays and a monotonic clock, counted by SysTick
+//!
+//! SysTick counts down from [`RELOAD`] at the system clock's frequency and raises an exception
+//! every time it wraps around. Counting the wraps (in 64 bits) on top of the 24-bit counter gives a 64-bit tick count
+//! that (at 80 MHz) would take thousands of years to overflow
+//!
+//! The delays and timeouts don't rely on the exception: they read the counter themselves, so they still work
+//! where it can't run (in an interrupt handler, a critical section, or the panic handler)
+//!
+//! Page 123 of data sheet (System Timer (SysTick))
+
+use core::cell::Cell;
+use core::ops::{Add, Sub};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::interrupts::{self, Mutex};
+use crate::registers::core_peripherals::{BASE, INTCTRL, STCTRL, STCURRENT, STRELOAD};
+use crate::registers::{Bits, Register};
+
+/// The largest value SysTick's 24-bit counter can count down from
+const RELOAD: u32 = 0xFF_FFFF;
+const TICKS_PER_WRAP: u64 = RELOAD as u64 + 1;
+
+/// The frequency SysTick counts at (the system clock's)
+static TICKS_PER_SECOND: AtomicU32 = AtomicU32::new(16_000_000);
+/// How many times SysTick has wrapped around
+///
+/// There are no 64-bit atomics on the Cortex-M4, so it's only touched in a critical section
+static WRAPS: Mutex<Cell<u64>> = Mutex::new(Cell::new(0));
+
+const fn control() -> Register<STCTRL> {
+    Register::new(BASE)
+}
+const fn reload() -> Register<STRELOAD> {
+    Register::new(BASE)
+}
+const fn current() -> Register<STCURRENT> {
+    Register::new(BASE)
+}
+const fn interrupt_control() -> Register<INTCTRL> {
+    Register::new(BASE)
+}
+
+/// Page 123 of data sheet (SysTick initialization)
+pub(crate) fn setup_systick(system_clock_hz: u32) {
+    TICKS_PER_SECOND.store(system_clock_hz, Ordering::Relaxed);
+
+    // Stop the counter while it's being set up
+    control().write(0);
+
+    reload().modify(STRELOAD::RELOAD, Bits::<24>::new(RELOAD));
+
+    // Writing any value clears the counter (and the COUNT flag)
+    current().write(0);
+
+    control().write(STCTRL::ENABLE.mask() | STCTRL::INTEN.mask() | STCTRL::CLK_SRC.mask());
+}
+
+#[cfg(not(test))]
+#[cortex_m_rt::exception]
+fn SysTick() {
+    interrupts::free(|critical_section| {
+        let wraps = WRAPS.borrow(critical_section);
+        wraps.set(wraps.get() + 1);
+    });
+}
+
+/// A point in time, counted in system clock ticks since SysTick was set up
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant {
+    ticks: u64,
+}
+
+impl Instant {
+    /// Only the wraps the SysTick exception has run for (plus one that's pending) are counted,
+    /// so where it can't run, this falls behind once the counter has wrapped twice (about a second at 16 MHz)
+    pub fn now() -> Self {
+        interrupts::free(|critical_section| {
+            let mut wraps = WRAPS.borrow(critical_section).get();
+            let mut counted: Bits<24> = current().read_field(STCURRENT::CURRENT);
+
+            // With interrupts disabled, a wrap that just happened hasn't been counted by the exception yet.
+            // It may have happened before or after the counter was read, so read it again to be sure it's after
+            if interrupt_control().read_field(INTCTRL::PENDSTSET) {
+                wraps += 1;
+                counted = current().read_field(STCURRENT::CURRENT);
+            }
+
+            Instant {
+                ticks: wraps * TICKS_PER_WRAP + (RELOAD - counted.value()) as u64,
+            }
+        })
+    }
+
+    pub const fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// How long it's been since `earlier` (or no time at all if `earlier` is actually later)
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        Duration::from_ticks(self.ticks.saturating_sub(earlier.ticks))
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        Instant {
+            ticks: self.ticks + duration.ticks,
+        }
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// A span of time, counted in system clock ticks
+///
+/// Converting from and to real units uses the frequency the system clock was set up with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Duration {
+    ticks: u64,
+}
+
+impl Duration {
+    pub const fn from_ticks(ticks: u64) -> Self {
+        Duration { ticks }
+    }
+    pub fn from_micros(micros: u64) -> Self {
+        Duration::from_ticks(micros * ticks_per_second() / 1_000_000)
+    }
+    pub fn from_millis(millis: u64) -> Self {
+        Duration::from_ticks(millis * ticks_per_second() / 1_000)
+    }
+
+    pub const fn ticks(&self) -> u64 {
+        self.ticks
+    }
+    pub fn as_micros(&self) -> u64 {
+        self.ticks * 1_000_000 / ticks_per_second()
+    }
+    pub fn as_millis(&self) -> u64 {
+        self.ticks * 1_000 / ticks_per_second()
+    }
+}
+
+/// The frequency the system clock was last set up to run at (or the precision internal oscillator's, out of reset)
+#[cfg(not(test))]
+pub(crate) fn system_clock_hz() -> u32 {
+    TICKS_PER_SECOND.load(Ordering::Relaxed)
+}
+
+/// Whether SysTick is counting (it isn't until the board has been set up)
+#[cfg(not(test))]
+pub(crate) fn systick_enabled() -> bool {
+    control().read_field(STCTRL::ENABLE)
+}
+
+fn ticks_per_second() -> u64 {
+    TICKS_PER_SECOND.load(Ordering::Relaxed) as u64
+}
+
+/// Counts the ticks that go by by reading the counter itself, without relying on the SysTick exception
+///
+/// It has to be checked at least once per wrap of the counter (more than a second at 16 MHz)
+pub(crate) struct Stopwatch {
+    counted: u32,
+    elapsed: u64,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Stopwatch {
+            counted: counter(),
+            elapsed: 0,
+        }
+    }
+
+    pub(crate) fn elapsed(&mut self) -> Duration {
+        let counted = counter();
+
+        // The counter counts down, and goes from 0 back up to RELOAD
+        self.elapsed += (self.counted.wrapping_sub(counted) & RELOAD) as u64;
+        self.counted = counted;
+
+        Duration::from_ticks(self.elapsed)
+    }
+}
+
+fn counter() -> u32 {
+    current()
+        .read_field::<24, Bits<24>>(STCURRENT::CURRENT)
+        .value()
+}
+
+/// Busy-wait for (at least) `duration`
+pub fn delay(duration: Duration) {
+    let mut stopwatch = Stopwatch::start();
+
+    while stopwatch.elapsed() < duration {}
+}
+
+pub fn delay_ms(millis: u32) {
+    delay(Duration::from_millis(millis as u64));
+}
+
+pub fn delay_us(micros: u32) {
+    delay(Duration::from_micros(micros as u64));
+}
+
+/// Keep calling `poll` until it returns something, giving up (with `None`) once `timeout` has passed
+pub fn with_timeout<R>(timeout: Duration, mut poll: impl FnMut() -> Option<R>) -> Option<R> {
+    let mut stopwatch = Stopwatch::start();
+
+    loop {
+        if let Some(result) = poll() {
+            return Some(result);
+        }
+
+        if stopwatch.elapsed() >= timeout {
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::simulated;
+
+    #[test]
+    fn addresses_match_the_data_sheet() {
+        assert_eq!(control().address() as u32, 0xE000_E010);
+        assert_eq!(reload().address() as u32, 0xE000_E014);
+        assert_eq!(current().address() as u32, 0xE000_E018);
+        assert_eq!(interrupt_control().address() as u32, 0xE000_ED04);
+    }
+
+    #[test]
+    fn setup_counts_the_system_clock_from_the_top() {
+        setup_systick(16_000_000);
+
+        simulated::with(|memory| {
+            assert_eq!(memory.value(reload().address()), 0xFF_FFFF);
+            assert_eq!(memory.value(control().address()), 0b111);
+            assert_eq!(
+                memory.written_registers(),
+                [
+                    control().address(),
+                    reload().address(),
+                    current().address(),
+                    control().address(),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn now_counts_a_wrap_that_is_still_pending() {
+        simulated::with(|memory| {
+            memory.preset(current().address(), RELOAD - 10);
+        });
+        let before = Instant::now();
+
+        simulated::with(|memory| {
+            memory.preset(interrupt_control().address(), INTCTRL::PENDSTSET.mask());
+            memory.preset(current().address(), RELOAD - 5);
+        });
+        let after = Instant::now();
+
+        assert_eq!(after - before, Duration::from_ticks(TICKS_PER_WRAP - 5));
+        assert_eq!(before - after, Duration::from_ticks(0));
+    }
+
+    #[test]
+    fn stopwatch_counts_wraps_without_the_exception() {
+        simulated::with(|memory| memory.preset(current().address(), 100));
+        let mut stopwatch = Stopwatch::start();
+
+        simulated::with(|memory| memory.preset(current().address(), 40));
+        assert_eq!(stopwatch.elapsed(), Duration::from_ticks(60));
+
+        // Wrapped around (with no exception to count it)
+        simulated::with(|memory| memory.preset(current().address(), RELOAD - 9));
+        assert_eq!(stopwatch.elapsed(), Duration::from_ticks(60 + 40 + 10));
+    }
+
+    #[test]
+    fn durations_convert_with_the_system_clock() {
+        assert_eq!(Duration::from_millis(3).ticks(), 48_000);
+        assert_eq!(Duration::from_micros(1).ticks(), 16);
+        assert_eq!(Duration::from_ticks(48_000).as_micros(), 3_000);
+    }
+}
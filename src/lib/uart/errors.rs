@@ -0,0 +1,109 @@
+//! Receive errors
+//!
+//! Every byte read out of the receive FIFO comes with 4 error bits (page 906 of data sheet, DR).
+//! They're counted for each port as soon as the byte leaves the FIFO, and a byte that came in wrong
+//! is handed to the program as an error instead of as data
+
+use core::cell::Cell;
+
+use crate::interrupts::{self, Mutex};
+
+use super::Port;
+
+/// What went wrong receiving a byte
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceiveError {
+    /// The line was held low for longer than a whole frame (the byte is all 0s and isn't data)
+    Break,
+    /// The byte didn't end with a valid stop bit
+    Framing,
+    /// The byte's parity bit didn't match (only checked when the port has parity)
+    Parity,
+    /// Bytes after this one were lost because the receive FIFO was full (this byte itself came in fine)
+    Overrun(u8),
+}
+
+/// The error bits that come with each received byte
+///
+/// Page 906 of data sheet (FE, PE, BE, and OE)
+const FRAMING: u32 = 1 << 8;
+const PARITY: u32 = 1 << 9;
+const BREAK: u32 = 1 << 10;
+const OVERRUN: u32 = 1 << 11;
+
+impl ReceiveError {
+    /// The byte from the data register (and its error bits), or what went wrong receiving it
+    pub(super) fn check(data: u32) -> Result<u8, ReceiveError> {
+        let byte = data as u8;
+
+        // A break also sets the framing error bit (and parity too, with even parity), so it's checked first
+        if data & BREAK != 0 {
+            Err(ReceiveError::Break)
+        } else if data & FRAMING != 0 {
+            Err(ReceiveError::Framing)
+        } else if data & PARITY != 0 {
+            Err(ReceiveError::Parity)
+        } else if data & OVERRUN != 0 {
+            Err(ReceiveError::Overrun(byte))
+        } else {
+            Ok(byte)
+        }
+    }
+}
+
+/// How many times each kind of receive error has happened on a port
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ErrorCounts {
+    pub breaks: u32,
+    pub framing: u32,
+    pub parity: u32,
+    pub overruns: u32,
+    /// Bytes dropped because the port's receive buffer was full (only when its interrupts are enabled)
+    pub dropped: u32,
+}
+
+const PORTS: usize = 8;
+
+static ERROR_COUNTS: [Mutex<Cell<ErrorCounts>>; PORTS] = [const {
+    Mutex::new(Cell::new(ErrorCounts {
+        breaks: 0,
+        framing: 0,
+        parity: 0,
+        overruns: 0,
+        dropped: 0,
+    }))
+}; PORTS];
+
+fn update(port: Port, updater: impl FnOnce(&mut ErrorCounts)) {
+    interrupts::free(|critical_section| {
+        let counts = ERROR_COUNTS[port as usize].borrow(critical_section);
+        let mut updated = counts.get();
+        updater(&mut updated);
+        counts.set(updated);
+    });
+}
+
+/// Count the errors that came with a byte just read out of the receive FIFO
+pub(super) fn record(port: Port, data: u32) {
+    if let Err(error) = ReceiveError::check(data) {
+        update(port, |counts| match error {
+            ReceiveError::Break => counts.breaks += 1,
+            ReceiveError::Framing => counts.framing += 1,
+            ReceiveError::Parity => counts.parity += 1,
+            ReceiveError::Overrun(_) => counts.overruns += 1,
+        });
+    }
+}
+
+/// Count a byte that didn't fit in the receive buffer
+pub(super) fn record_dropped(port: Port) {
+    update(port, |counts| counts.dropped += 1);
+}
+
+pub(super) fn counts(port: Port) -> ErrorCounts {
+    interrupts::free(|critical_section| ERROR_COUNTS[port as usize].borrow(critical_section).get())
+}
+
+pub(super) fn clear(port: Port) {
+    update(port, |counts| *counts = ErrorCounts::default());
+}
@@ -0,0 +1,379 @@
+//! UART interrupts and the ring buffers they fill and drain
+//!
+//! Once a port's interrupts are enabled, its handler moves received bytes out of the hardware FIFO
+//! into the port's receive buffer (when the FIFO reaches its level, or when bytes have sat in it for a while),
+//! and moves bytes from the port's transmit buffer into the hardware FIFO whenever the FIFO drains to its level.
+//! The program only ever touches the buffers, so nothing has to wait on the UART
+//!
+//! Page 899 of data sheet (Interrupts)
+
+use core::cell::RefCell;
+
+use crate::interrupts::{self, CriticalSection, Mutex};
+use crate::registers::uart::{FR, IFLS, IM};
+use crate::registers::FieldValue;
+
+use super::{errors, Port, RECEIVED_BITS};
+
+/// How full (for receiving) or empty (for transmitting) the 16-byte hardware FIFO gets before it raises an interrupt
+///
+/// Page 922 of data sheet (RXIFLSEL and TXIFLSEL field encodings)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FifoLevel {
+    OneEighth,
+    OneQuarter,
+    Half,
+    ThreeQuarters,
+    SevenEighths,
+}
+
+impl FieldValue<3> for FifoLevel {
+    fn into_bits(self) -> u32 {
+        match self {
+            FifoLevel::OneEighth => 0x0,
+            FifoLevel::OneQuarter => 0x1,
+            FifoLevel::Half => 0x2,
+            FifoLevel::ThreeQuarters => 0x3,
+            FifoLevel::SevenEighths => 0x4,
+        }
+    }
+    fn from_bits(bits: u32) -> Self {
+        match bits {
+            0x0 => FifoLevel::OneEighth,
+            0x1 => FifoLevel::OneQuarter,
+            0x2 => FifoLevel::Half,
+            0x3 => FifoLevel::ThreeQuarters,
+            _ => FifoLevel::SevenEighths,
+        }
+    }
+}
+
+pub struct InterruptOptions {
+    /// Empty the receive FIFO once it's at least this full
+    /// (it's also emptied once bytes have been waiting in it for 32 bits' time)
+    pub receive_level: FifoLevel,
+    /// Refill the transmit FIFO once it's at most this full
+    pub transmit_level: FifoLevel,
+}
+
+impl Default for InterruptOptions {
+    /// Half full and half empty, which is how the FIFO levels come out of reset
+    fn default() -> Self {
+        InterruptOptions {
+            receive_level: FifoLevel::Half,
+            transmit_level: FifoLevel::Half,
+        }
+    }
+}
+
+/// How many bytes each port's receive and transmit buffers can hold
+pub const BUFFER_SIZE: usize = 64;
+
+/// How many bytes the hardware FIFOs hold (page 898 of data sheet)
+const FIFO_SIZE: usize = 16;
+
+/// A first-in first-out queue that drops new entries once it's full
+struct RingBuffer<T> {
+    entries: [T; BUFFER_SIZE],
+    start: usize,
+    len: usize,
+}
+
+impl<T: Copy> RingBuffer<T> {
+    const fn new(empty: T) -> Self {
+        RingBuffer {
+            entries: [empty; BUFFER_SIZE],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    /// Whether there was room for `entry`
+    fn push(&mut self, entry: T) -> bool {
+        if self.len == BUFFER_SIZE {
+            return false;
+        }
+
+        self.entries[(self.start + self.len) % BUFFER_SIZE] = entry;
+        self.len += 1;
+        true
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let entry = self.entries[self.start];
+        self.start = (self.start + 1) % BUFFER_SIZE;
+        self.len -= 1;
+        Some(entry)
+    }
+
+    fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+    }
+}
+
+const PORTS: usize = 8;
+
+/// Received bytes are kept with their error bits (the low 12 bits of the data register)
+static RECEIVE_BUFFERS: [Mutex<RefCell<RingBuffer<u16>>>; PORTS] =
+    [const { Mutex::new(RefCell::new(RingBuffer::new(0))) }; PORTS];
+static TRANSMIT_BUFFERS: [Mutex<RefCell<RingBuffer<u8>>>; PORTS] =
+    [const { Mutex::new(RefCell::new(RingBuffer::new(0))) }; PORTS];
+
+/// The receive (RX), receive time-out (RT), and transmit (TX) interrupts, which are in the same place
+/// in the mask, status, and clear registers
+///
+/// Page 924 of data sheet
+const RECEIVE: u32 = 1 << 4;
+const TRANSMIT: u32 = 1 << 5;
+const RECEIVE_TIME_OUT: u32 = 1 << 6;
+
+/// Start moving bytes between the port's FIFOs and its buffers from its interrupt handler
+/// (both buffers start out empty)
+pub(super) fn enable(port: Port, options: InterruptOptions) {
+    interrupts::free(|critical_section| {
+        RECEIVE_BUFFERS[port as usize]
+            .borrow(critical_section)
+            .borrow_mut()
+            .clear();
+        TRANSMIT_BUFFERS[port as usize]
+            .borrow(critical_section)
+            .borrow_mut()
+            .clear();
+
+        let levels = port.interrupt_fifo_level_select();
+        levels.modify(IFLS::RX, options.receive_level);
+        levels.modify(IFLS::TX, options.transmit_level);
+
+        // Forget anything raised before now
+        port.interrupt_clear()
+            .write(RECEIVE | TRANSMIT | RECEIVE_TIME_OUT);
+
+        // The transmit interrupt is only unmasked while there's something to send
+        let mask = port.interrupt_mask();
+        mask.modify(IM::RXIM, true);
+        mask.modify(IM::RTIM, true);
+    });
+
+    interrupts::enable(port.interrupt());
+}
+
+/// Stop the port's interrupts (whatever's left in its buffers is dropped)
+pub(super) fn disable(port: Port) {
+    interrupts::disable(port.interrupt());
+
+    interrupts::free(|_| {
+        port.interrupt_mask()
+            .update(&|current| current & !(RECEIVE | TRANSMIT | RECEIVE_TIME_OUT));
+    });
+}
+
+/// Take the oldest received byte (with its error bits), if there is one
+pub(super) fn receive(port: Port) -> Option<u32> {
+    interrupts::free(|critical_section| {
+        RECEIVE_BUFFERS[port as usize]
+            .borrow(critical_section)
+            .borrow_mut()
+            .pop()
+            .map(u32::from)
+    })
+}
+
+/// Queue up as many of `bytes` as there's room for, returning how many that was
+pub(super) fn write(port: Port, bytes: &[u8]) -> usize {
+    interrupts::free(|critical_section| {
+        let mut transmit_buffer = TRANSMIT_BUFFERS[port as usize]
+            .borrow(critical_section)
+            .borrow_mut();
+
+        let count = bytes
+            .iter()
+            .take_while(|byte| transmit_buffer.push(**byte))
+            .count();
+
+        // The transmit interrupt only fires when the FIFO drains past its level,
+        // so an idle FIFO has to be started off here
+        fill_transmit_fifo(port, &mut transmit_buffer, critical_section);
+        count
+    })
+}
+
+/// Whether everything queued up has gone into the hardware FIFO
+pub(super) fn transmit_buffer_empty(port: Port) -> bool {
+    interrupts::free(|critical_section| {
+        TRANSMIT_BUFFERS[port as usize]
+            .borrow(critical_section)
+            .borrow()
+            .len
+            == 0
+    })
+}
+
+fn fill_transmit_fifo(port: Port, transmit_buffer: &mut RingBuffer<u8>, _: &CriticalSection) {
+    while !port.flag().read_field::<1, bool>(FR::TXFF) {
+        let Some(byte) = transmit_buffer.pop() else {
+            break;
+        };
+
+        port.data().write(byte as u32);
+    }
+
+    // Only ask to be told when the FIFO drains if there's more to put in it
+    port.interrupt_mask()
+        .modify(IM::TXIM, transmit_buffer.len != 0);
+}
+
+/// Move received bytes into the port's receive buffer and queued bytes into its transmit FIFO
+pub(crate) fn handle(port: Port) {
+    let triggered = port.masked_interrupt_status().read();
+    port.interrupt_clear().write(triggered);
+
+    interrupts::free(|critical_section| {
+        if triggered & (RECEIVE | RECEIVE_TIME_OUT) != 0 {
+            let mut receive_buffer = RECEIVE_BUFFERS[port as usize]
+                .borrow(critical_section)
+                .borrow_mut();
+
+            // The FIFO can't hold more than this, so there's no need to keep checking after
+            for _ in 0..FIFO_SIZE {
+                if port.flag().read_field::<1, bool>(FR::RXFE) {
+                    break;
+                }
+
+                let data = port.data().read() & RECEIVED_BITS;
+                errors::record(port, data);
+
+                // Bytes that don't fit are dropped (the oldest ones are kept)
+                if !receive_buffer.push(data as u16) {
+                    errors::record_dropped(port);
+                }
+            }
+        }
+
+        if triggered & TRANSMIT != 0 {
+            let mut transmit_buffer = TRANSMIT_BUFFERS[port as usize]
+                .borrow(critical_section)
+                .borrow_mut();
+
+            fill_transmit_fifo(port, &mut transmit_buffer, critical_section);
+        }
+    });
+}
+
+/// The port interrupt handlers named in the vector table
+#[cfg(not(test))]
+mod vectors {
+    use super::{handle, Port};
+
+    #[no_mangle]
+    extern "C" fn UART0() {
+        handle(Port::Zero);
+    }
+    #[no_mangle]
+    extern "C" fn UART1() {
+        handle(Port::One);
+    }
+    #[no_mangle]
+    extern "C" fn UART2() {
+        handle(Port::Two);
+    }
+    #[no_mangle]
+    extern "C" fn UART3() {
+        handle(Port::Three);
+    }
+    #[no_mangle]
+    extern "C" fn UART4() {
+        handle(Port::Four);
+    }
+    #[no_mangle]
+    extern "C" fn UART5() {
+        handle(Port::Five);
+    }
+    #[no_mangle]
+    extern "C" fn UART6() {
+        handle(Port::Six);
+    }
+    #[no_mangle]
+    extern "C" fn UART7() {
+        handle(Port::Seven);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::simulated;
+
+    #[test]
+    fn received_bytes_are_buffered_until_read() {
+        let port = Port::Five;
+        enable(port, InterruptOptions::default());
+
+        simulated::with(|memory| {
+            assert_eq!(
+                memory.value(port.interrupt_mask().address()),
+                RECEIVE | RECEIVE_TIME_OUT
+            );
+            assert_eq!(
+                memory.value(port.interrupt_fifo_level_select().address()),
+                0x12
+            );
+
+            // A byte waiting in the FIFO (which never empties here, so the handler stops once it's read a FIFO's worth)
+            memory.preset(port.masked_interrupt_status().address(), RECEIVE_TIME_OUT);
+            memory.preset(port.flag().address(), 0);
+            memory.preset(port.data().address(), b'a' as u32);
+        });
+
+        handle(port);
+
+        for _ in 0..FIFO_SIZE {
+            assert_eq!(receive(port), Some(b'a' as u32));
+        }
+        assert_eq!(receive(port), None);
+    }
+
+    #[test]
+    fn written_bytes_wait_for_room_in_the_fifo() {
+        let port = Port::Six;
+        enable(port, InterruptOptions::default());
+
+        // The FIFO is full, so everything is queued up
+        simulated::with(|memory| memory.preset(port.flag().address(), 1 << 5));
+        assert_eq!(write(port, b"hi"), 2);
+        assert_eq!(write(port, &[0; BUFFER_SIZE]), BUFFER_SIZE - 2);
+        simulated::with(|memory| {
+            assert_eq!(
+                memory.value(port.interrupt_mask().address()) & TRANSMIT,
+                TRANSMIT
+            );
+            assert!(!memory
+                .writes()
+                .iter()
+                .any(|(address, _)| *address == port.data().address()));
+
+            memory.preset(port.flag().address(), 0);
+            memory.preset(port.masked_interrupt_status().address(), TRANSMIT);
+            memory.clear_accesses();
+        });
+
+        handle(port);
+
+        simulated::with(|memory| {
+            let sent: Vec<u32> = memory
+                .writes()
+                .iter()
+                .filter(|(address, _)| *address == port.data().address())
+                .map(|(_, value)| *value)
+                .collect();
+            assert_eq!(sent.len(), BUFFER_SIZE);
+            assert_eq!(sent[..2], [b'h' as u32, b'i' as u32]);
+            assert_eq!(memory.value(port.interrupt_mask().address()) & TRANSMIT, 0);
+        });
+        assert!(transmit_buffer_empty(port));
+    }
+}
@@ -0,0 +1,1017 @@
+use alloc::string::String;
+use core::cell::Cell;
+
+use crate::registers::sysctl::{PRUART, RCGCUART};
+use crate::registers::uart::{CTL, DR, ECR, FBRD, FR, IBRD, ICR, IFLS, IM, LCRH, MIS};
+use crate::registers::{interrupts as interrupt_numbers, peripherals, Field, FieldValue, Register};
+use crate::time::{self, Duration};
+use crate::{Board, Function, GPIOPort, Pin, ReadablePin, WritablePin};
+
+mod errors;
+mod interrupts;
+
+pub use errors::{ErrorCounts, ReceiveError};
+pub use interrupts::{FifoLevel, InterruptOptions, BUFFER_SIZE};
+
+/// The received byte and its error bits in the data register
+const RECEIVED_BITS: u32 = 0xFFF;
+
+#[derive(Clone, Copy)]
+pub enum Port {
+    Zero = 0,
+    One = 1,
+    Two = 2,
+    Three = 3,
+    Four = 4,
+    Five = 5,
+    Six = 6,
+    Seven = 7,
+}
+
+#[derive(Clone, Copy)]
+pub enum WordLength {
+    Five,
+    Six,
+    Seven,
+    Eight,
+}
+
+/// Page 916 of data sheet
+impl FieldValue<2> for WordLength {
+    fn into_bits(self) -> u32 {
+        match self {
+            WordLength::Five => 0x0,
+            WordLength::Six => 0x1,
+            WordLength::Seven => 0x2,
+            WordLength::Eight => 0x3,
+        }
+    }
+    fn from_bits(bits: u32) -> Self {
+        match bits {
+            0x0 => WordLength::Five,
+            0x1 => WordLength::Six,
+            0x2 => WordLength::Seven,
+            _ => WordLength::Eight,
+        }
+    }
+}
+
+/// The parity bit sent after the data bits (and checked on each byte received)
+///
+/// Page 916 of data sheet (PEN, EPS, and SPS)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    /// Makes the number of 1s (counting the parity bit) even
+    Even,
+    /// Makes the number of 1s (counting the parity bit) odd
+    Odd,
+    /// The parity bit is always 1
+    Mark,
+    /// The parity bit is always 0
+    Space,
+}
+
+impl Parity {
+    /// Whether each of the PEN, EPS, and SPS bits is set for this parity
+    const fn line_control_bits(self) -> (bool, bool, bool) {
+        match self {
+            Parity::None => (false, false, false),
+            Parity::Even => (true, true, false),
+            Parity::Odd => (true, false, false),
+            // With stick parity, the parity bit is the opposite of EPS
+            Parity::Mark => (true, false, true),
+            Parity::Space => (true, true, true),
+        }
+    }
+}
+
+/// Page 916 of data sheet (STP2)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+pub struct PortOptions {
+    pub baud_rate: u32,
+    pub fifos: bool,
+    pub word_length: WordLength,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+}
+
+/// The baud rate a port was asked for and the one it actually runs at,
+/// which can only be as close as the baud-rate divisor allows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BaudRate {
+    pub requested: u32,
+    pub achieved: u32,
+}
+
+impl BaudRate {
+    /// How far off the achieved baud rate is, as a percentage of the requested one (negative if it's too slow)
+    pub fn error_percent(&self) -> f32 {
+        (self.achieved as f32 - self.requested as f32) * 100.0 / self.requested as f32
+    }
+
+    /// Whether the achieved baud rate is within [`MAX_BAUD_RATE_ERROR_PERCENT`] of the requested one
+    const fn within_tolerance(&self) -> bool {
+        self.achieved.abs_diff(self.requested) as u64 * 100
+            <= self.requested as u64 * MAX_BAUD_RATE_ERROR_PERCENT as u64
+    }
+}
+
+/// How far off the achieved baud rate can be before a port won't be set up
+///
+/// Each end samples the middle of each bit, so together they can only be a few percent apart
+/// before the last bits of a frame are misread
+pub const MAX_BAUD_RATE_ERROR_PERCENT: u32 = 2;
+
+/// Why a port couldn't be set up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetupError {
+    /// The port is already set up
+    AlreadySetUp,
+    /// The baud rate is 0
+    ZeroBaudRate,
+    /// The baud rate can't be reached from the system clock (within [`MAX_BAUD_RATE_ERROR_PERCENT`]),
+    /// with the closest baud rate that can be
+    UnreachableBaudRate(BaudRate),
+}
+
+/// The baud-rate divisor registers' values for a baud rate
+#[derive(Debug, PartialEq, Eq)]
+struct BaudRateDivisor {
+    integer: u32,
+    fraction: u32,
+    /// Whether the UART runs at 8 times the baud rate instead of 16 (HSE)
+    high_speed: bool,
+    baud_rate: BaudRate,
+}
+
+/// The divisor is a 16-bit integer part and a 6-bit fraction, so it's worked out in 64ths
+const MIN_DIVISOR_64THS: u64 = 1 << 6;
+const MAX_DIVISOR_64THS: u64 = (0xFFFF << 6) | 0x3F;
+
+/// Page 896 of data sheet (Baud-Rate Generation): the divisor is the UART clock over ClkDiv times the baud rate,
+/// where ClkDiv is 16, or 8 when that's the only way to reach the baud rate
+fn baud_rate_divisor(system_clock_hz: u32, baud_rate: u32) -> Result<BaudRateDivisor, SetupError> {
+    if baud_rate == 0 {
+        return Err(SetupError::ZeroBaudRate);
+    }
+
+    // The divisor in 64ths, rounded to the nearest
+    let divisor_64ths = |clock_divider: u64| {
+        divide_rounded(
+            system_clock_hz as u64 * 64,
+            clock_divider * baud_rate as u64,
+        )
+    };
+
+    let high_speed = divisor_64ths(16) < MIN_DIVISOR_64THS;
+    let clock_divider = if high_speed { 8 } else { 16 };
+    // Out of range divisors are brought back in range to find the closest baud rate that can be reached
+    let divisor = divisor_64ths(clock_divider).clamp(MIN_DIVISOR_64THS, MAX_DIVISOR_64THS);
+
+    let achieved = divide_rounded(system_clock_hz as u64 * 64, clock_divider * divisor);
+    let baud_rate = BaudRate {
+        requested: baud_rate,
+        achieved: achieved as u32,
+    };
+    if !baud_rate.within_tolerance() {
+        return Err(SetupError::UnreachableBaudRate(baud_rate));
+    }
+
+    Ok(BaudRateDivisor {
+        integer: (divisor >> 6) as u32,
+        fraction: (divisor & 0x3F) as u32,
+        high_speed,
+        baud_rate,
+    })
+}
+
+fn divide_rounded(numerator: u64, denominator: u64) -> u64 {
+    (numerator + denominator / 2) / denominator
+}
+
+impl Port {
+    /// The starting point of memory addresses corresponding to this GPIO register
+    ///
+    /// Modeled after page 904 of data sheet (UART Register Map)
+    const fn base(&self) -> u32 {
+        match self {
+            Port::Zero => peripherals::UART0,
+            Port::One => peripherals::UART1,
+            Port::Two => peripherals::UART2,
+            Port::Three => peripherals::UART3,
+            Port::Four => peripherals::UART4,
+            Port::Five => peripherals::UART5,
+            Port::Six => peripherals::UART6,
+            Port::Seven => peripherals::UART7,
+        }
+    }
+
+    /// The control (CTL) register for this port
+    ///
+    /// Page 918 of data sheet
+    pub(super) const fn control(&self) -> Register<CTL> {
+        Register::new(self.base())
+    }
+
+    /// The data (DR) register for this port
+    ///
+    /// Page 906 of data sheet
+    pub(super) const fn data(&self) -> Register<DR> {
+        Register::new(self.base())
+    }
+
+    /// The error clear (ECR) register for this port
+    ///
+    /// Page 909 of data sheet
+    pub(super) const fn error_clear(&self) -> Register<ECR> {
+        Register::new(self.base())
+    }
+
+    /// The flag (FR) register for this port
+    ///
+    /// Page 911 of data sheet
+    pub(super) const fn flag(&self) -> Register<FR> {
+        Register::new(self.base())
+    }
+
+    /// The fractional baud-rate divisor (FBRD) register for this port
+    ///
+    /// Page 915 of data sheet
+    pub(super) const fn fractional_baud_rate_divisor(&self) -> Register<FBRD> {
+        Register::new(self.base())
+    }
+
+    /// The interrupt clear (ICR) register for this port
+    ///
+    /// Page 933 of data sheet
+    pub(super) const fn interrupt_clear(&self) -> Register<ICR> {
+        Register::new(self.base())
+    }
+
+    /// The interrupt FIFO level select (IFLS) register for this port
+    ///
+    /// Page 922 of data sheet
+    pub(super) const fn interrupt_fifo_level_select(&self) -> Register<IFLS> {
+        Register::new(self.base())
+    }
+
+    /// The interrupt mask (IM) register for this port
+    ///
+    /// Page 924 of data sheet
+    pub(super) const fn interrupt_mask(&self) -> Register<IM> {
+        Register::new(self.base())
+    }
+
+    /// The integer baud-rate divisor (IBRD) register for this port
+    ///
+    /// Page 914 of data sheet
+    pub(super) const fn integer_baud_rate_divisor(&self) -> Register<IBRD> {
+        Register::new(self.base())
+    }
+
+    /// The line control (LCRH) register for this port
+    ///
+    /// Page 916 of data sheet
+    pub(super) const fn line_control(&self) -> Register<LCRH> {
+        Register::new(self.base())
+    }
+
+    /// The masked interrupt status (MIS) register for this port
+    ///
+    /// Page 930 of data sheet
+    pub(super) const fn masked_interrupt_status(&self) -> Register<MIS> {
+        Register::new(self.base())
+    }
+}
+
+impl Port {
+    /// This port's interrupt number in the NVIC
+    ///
+    /// Page 104 of data sheet (Interrupts)
+    const fn interrupt(&self) -> u32 {
+        match self {
+            Port::Zero => interrupt_numbers::UART0,
+            Port::One => interrupt_numbers::UART1,
+            Port::Two => interrupt_numbers::UART2,
+            Port::Three => interrupt_numbers::UART3,
+            Port::Four => interrupt_numbers::UART4,
+            Port::Five => interrupt_numbers::UART5,
+            Port::Six => interrupt_numbers::UART6,
+            Port::Seven => interrupt_numbers::UART7,
+        }
+    }
+
+    /// The corresponding field for this port in the system's UART Run mode clock gating control (RCGCUART) register
+    const fn run_mode_clock_gating_control_field(&self) -> Field<RCGCUART, 1> {
+        match self {
+            Port::Zero => RCGCUART::R0,
+            Port::One => RCGCUART::R1,
+            Port::Two => RCGCUART::R2,
+            Port::Three => RCGCUART::R3,
+            Port::Four => RCGCUART::R4,
+            Port::Five => RCGCUART::R5,
+            Port::Six => RCGCUART::R6,
+            Port::Seven => RCGCUART::R7,
+        }
+    }
+
+    /// The corresponding field for this port in the system's UART peripheral ready (PRUART) register
+    const fn peripheral_ready_field(&self) -> Field<PRUART, 1> {
+        match self {
+            Port::Zero => PRUART::R0,
+            Port::One => PRUART::R1,
+            Port::Two => PRUART::R2,
+            Port::Three => PRUART::R3,
+            Port::Four => PRUART::R4,
+            Port::Five => PRUART::R5,
+            Port::Six => PRUART::R6,
+            Port::Seven => PRUART::R7,
+        }
+    }
+
+    /// The pins this port's receive (Rx) and transmit (Tx) signals can be set up on
+    ///
+    /// Table 14-1 on page 895 of data sheet (UART Signals)
+    pub const fn pin_assignments(&self) -> &'static [PinAssignment] {
+        PIN_ASSIGNMENTS[*self as usize]
+    }
+}
+
+/// A pair of pins a port's signals can be set up on, and the functions to set them up with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PinAssignment {
+    pub gpio_port: GPIOPort,
+    pub receive_pin: Pin,
+    pub transmit_pin: Pin,
+    pub receive_function: Function,
+    pub transmit_function: Function,
+}
+
+const fn assignment(
+    gpio_port: GPIOPort,
+    (receive_pin, transmit_pin): (Pin, Pin),
+    (receive_function, transmit_function): (Function, Function),
+) -> PinAssignment {
+    PinAssignment {
+        gpio_port,
+        receive_pin,
+        transmit_pin,
+        receive_function,
+        transmit_function,
+    }
+}
+
+/// Each port's pin assignments, in port order
+const PIN_ASSIGNMENTS: [&[PinAssignment]; 8] = [
+    &[assignment(
+        GPIOPort::A,
+        (Pin::Zero, Pin::One),
+        (Function::U0Rx, Function::U0Tx),
+    )],
+    &[
+        assignment(
+            GPIOPort::B,
+            (Pin::Zero, Pin::One),
+            (Function::U1Rx, Function::U1Tx),
+        ),
+        assignment(
+            GPIOPort::C,
+            (Pin::Four, Pin::Five),
+            (Function::U1Rx, Function::U1Tx),
+        ),
+    ],
+    // PD7 can also be the NMI, so port D has to be set up with `unlock_nmi_pins` for it
+    &[assignment(
+        GPIOPort::D,
+        (Pin::Six, Pin::Seven),
+        (Function::U2Rx, Function::U2Tx),
+    )],
+    &[assignment(
+        GPIOPort::C,
+        (Pin::Six, Pin::Seven),
+        (Function::U3Rx, Function::U3Tx),
+    )],
+    &[assignment(
+        GPIOPort::C,
+        (Pin::Four, Pin::Five),
+        (Function::U4Rx, Function::U4Tx),
+    )],
+    &[assignment(
+        GPIOPort::E,
+        (Pin::Four, Pin::Five),
+        (Function::U5Rx, Function::U5Tx),
+    )],
+    &[assignment(
+        GPIOPort::D,
+        (Pin::Four, Pin::Five),
+        (Function::U6Rx, Function::U6Tx),
+    )],
+    &[assignment(
+        GPIOPort::E,
+        (Pin::Zero, Pin::One),
+        (Function::U7Rx, Function::U7Tx),
+    )],
+];
+
+pub struct UsablePort {
+    port: Port,
+    baud_rate: BaudRate,
+    /// Whether the port's interrupt handler is moving bytes between its FIFOs and its buffers
+    buffered: bool,
+    /// An error found by a read that had already read some bytes, for the next read to return
+    pending_error: Cell<Option<ReceiveError>>,
+}
+
+impl UsablePort {
+    pub fn baud_rate(&self) -> BaudRate {
+        self.baud_rate
+    }
+
+    /// Move bytes between the hardware FIFOs and software buffers from the port's interrupt handler from now on,
+    /// so bytes aren't lost while the program is busy (as long as the buffers don't fill up)
+    ///
+    /// Page 899 of data sheet (Interrupts)
+    pub fn enable_interrupts(&mut self, options: InterruptOptions) {
+        interrupts::enable(self.port, options);
+        self.buffered = true;
+    }
+
+    /// Go back to reading and writing the hardware FIFOs directly
+    ///
+    /// Waits for everything already written to go into the transmit FIFO, but drops anything received and not read yet
+    pub fn disable_interrupts(&mut self) {
+        while !interrupts::transmit_buffer_empty(self.port) {}
+
+        interrupts::disable(self.port);
+        self.buffered = false;
+    }
+
+    /// Read as many bytes as have been received (up to `bytes.len()`) without waiting, returning how many that was
+    ///
+    /// A byte that came in wrong stops the read there, and is returned as the error
+    /// on its own (by this call if it's the first byte, otherwise by the next read)
+    pub fn read(
+        &mut self,
+        _receive_pin: &ReadablePin,
+        bytes: &mut [u8],
+    ) -> Result<usize, ReceiveError> {
+        let mut count = 0;
+
+        for slot in bytes {
+            match self.receive() {
+                None => break,
+                Some(Ok(byte)) => *slot = byte,
+                Some(Err(error)) if count == 0 => return Err(error),
+                Some(Err(error)) => {
+                    self.pending_error.set(Some(error));
+                    break;
+                }
+            }
+
+            count += 1;
+        }
+
+        Ok(count)
+    }
+    /// Write as many of `bytes` as there's room for without waiting, returning how many that was
+    pub fn write(&mut self, _transmit_pin: &mut WritablePin, bytes: &[u8]) -> usize {
+        if self.buffered {
+            return interrupts::write(self.port, bytes);
+        }
+
+        let mut count = 0;
+        for byte in bytes {
+            if self.port.flag().read_field::<1, bool>(FR::TXFF) {
+                break;
+            }
+
+            self.port.data().write(*byte as u32);
+            count += 1;
+        }
+        count
+    }
+
+    /// Hold the transmit line low for `duration` (which the other end sees as a break), once everything already
+    /// written has been sent
+    ///
+    /// The line is held low for at least 2 frames, since that's the shortest break the UART guarantees
+    /// to get right (page 916 of data sheet, BRK)
+    pub fn send_break(&mut self, _transmit_pin: &mut WritablePin, duration: Duration) {
+        while self.buffered && !interrupts::transmit_buffer_empty(self.port) {}
+        while self.port.flag().read_field::<1, bool>(FR::BUSY) {}
+
+        // The longest frame is a start bit, 8 data bits, a parity bit, and 2 stop bits
+        let two_frames =
+            Duration::from_micros(2 * 12 * 1_000_000 / self.baud_rate.achieved as u64 + 1);
+
+        let line_control = self.port.line_control();
+        line_control.modify(LCRH::BRK, true);
+        time::delay(duration.max(two_frames));
+        line_control.modify(LCRH::BRK, false);
+    }
+
+    /// The next byte received (or what went wrong receiving it), or `None` if there isn't one yet and `blocking` is false
+    pub fn read_byte(
+        &self,
+        _receive_pin: &ReadablePin,
+        blocking: bool,
+    ) -> Option<Result<u8, ReceiveError>> {
+        loop {
+            if let Some(received) = self.receive() {
+                return Some(received);
+            }
+
+            if !blocking {
+                return None;
+            }
+        }
+    }
+    pub fn write_byte(&mut self, transmit_pin: &mut WritablePin, byte: u8, blocking: bool) -> bool {
+        loop {
+            if self.write(transmit_pin, &[byte]) == 1 {
+                return true;
+            }
+
+            if !blocking {
+                return false;
+            }
+        }
+    }
+
+    fn receive(&self) -> Option<Result<u8, ReceiveError>> {
+        if let Some(error) = self.pending_error.take() {
+            return Some(Err(error));
+        }
+
+        if self.buffered {
+            return interrupts::receive(self.port).map(ReceiveError::check);
+        }
+
+        if self.port.flag().read_field::<1, bool>(FR::RXFE) {
+            return None;
+        }
+
+        let data = self.port.data().read() & RECEIVED_BITS;
+        errors::record(self.port, data);
+        Some(ReceiveError::check(data))
+    }
+
+    /// How many of each kind of receive error there have been since the port was set up
+    /// (or since [`UsablePort::clear_errors`])
+    pub fn error_counts(&self) -> ErrorCounts {
+        errors::counts(self.port)
+    }
+
+    /// Reset the error counts, the receive status register, and any error a read is still waiting to return
+    ///
+    /// Page 909 of data sheet (ECR)
+    pub fn clear_errors(&mut self) {
+        self.port.error_clear().write(0);
+        errors::clear(self.port);
+        self.pending_error.set(None);
+    }
+
+    pub fn write_string(&mut self, _transmit_pin: &mut WritablePin, string: &str) {
+        for byte in string.bytes() {
+            self.write_byte(_transmit_pin, byte, true);
+        }
+    }
+
+    pub fn write_line(&mut self, _transmit_pin: &mut WritablePin, string: &str) {
+        self.write_string(_transmit_pin, string);
+        self.write_string(_transmit_pin, "\r\n");
+    }
+
+    pub fn read_line(
+        &mut self,
+        _transmit_pin: &mut WritablePin,
+        _receive_pin: &ReadablePin,
+    ) -> String {
+        let mut s = String::new();
+
+        loop {
+            // Bytes that came in wrong are left out
+            if let Some(Ok(c)) = self.read_byte(_receive_pin, true) {
+                // Enter
+                if c == b'\r' {
+                    self.write_string(_transmit_pin, "\r\n");
+                    return s;
+                }
+                // Backspace
+                else if c == b'\x7F' {
+                    if !s.is_empty() {
+                        // https://stackoverflow.com/a/53976873
+                        self.write_string(_transmit_pin, "\x1B[1D\x1B[1P");
+                        s.pop();
+                    }
+                } else {
+                    self.write_byte(_transmit_pin, c, true);
+                    s.push(c as char);
+                }
+            }
+        }
+    }
+}
+
+pub fn setup_port(
+    board: Board,
+    port: Port,
+    options: PortOptions,
+    system_clock_hz: u32,
+) -> Result<UsablePort, SetupError> {
+    // Worked out first so nothing is touched if the baud rate can't be reached
+    let divisor = baud_rate_divisor(system_clock_hz, options.baud_rate)?;
+
+    // Activate the associated peripheral and wait for it to be ready to use
+    board
+        .uart_run_mode_clock_gating_control()
+        .modify_atomic(port.run_mode_clock_gating_control_field(), true);
+    while !board
+        .uart_peripheral_ready()
+        .read_field::<1, bool>(port.peripheral_ready_field())
+    {}
+
+    // Disable this UART port while setting it up
+    port.control().modify(CTL::UARTEN, false);
+
+    // Page 896 of data sheet (Baud-Rate Generation)
+    port.control().modify(CTL::HSE, divisor.high_speed);
+    port.integer_baud_rate_divisor().write(divisor.integer);
+    port.fractional_baud_rate_divisor().write(divisor.fraction);
+
+    let line_control = port.line_control();
+
+    // Set the word length
+    line_control.modify(LCRH::WLEN, options.word_length);
+
+    // Set the parity and stop bits
+    let (parity_enable, even_parity, stick_parity) = options.parity.line_control_bits();
+    line_control.modify(LCRH::PEN, parity_enable);
+    line_control.modify(LCRH::EPS, even_parity);
+    line_control.modify(LCRH::SPS, stick_parity);
+    line_control.modify(LCRH::STP2, options.stop_bits == StopBits::Two);
+
+    // Enable or disable FIFOs
+    line_control.modify(LCRH::FEN, options.fifos);
+
+    // Not sending a break
+    line_control.modify(LCRH::BRK, false);
+
+    // Enable this UART port
+    port.control().modify(CTL::UARTEN, true);
+
+    Ok(UsablePort {
+        port,
+        baud_rate: divisor.baud_rate,
+        buffered: false,
+        pending_error: Cell::new(None),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpio::functions::mux;
+    use crate::gpio::pins::steal_readable_pins;
+    use crate::memory::{bit_band, simulated};
+    use crate::{Pull, ReadablePinOptions};
+
+    /// Every port is ready as soon as its clock is enabled (otherwise setup would wait forever)
+    fn peripherals_ready() {
+        simulated::with(|memory| {
+            memory.preset(Board.uart_peripheral_ready().address(), 0xFF);
+        });
+    }
+
+    /// The data sheet's addresses for the registers the UART driver uses, checked against the generated register map
+    #[test]
+    fn addresses_match_the_data_sheet() {
+        let ports = [
+            (Port::Zero, 0x4000_C000),
+            (Port::One, 0x4000_D000),
+            (Port::Two, 0x4000_E000),
+            (Port::Three, 0x4000_F000),
+            (Port::Four, 0x4001_0000),
+            (Port::Five, 0x4001_1000),
+            (Port::Six, 0x4001_2000),
+            (Port::Seven, 0x4001_3000),
+        ];
+
+        for (port, base) in ports {
+            assert_eq!(port.data().address() as u32, base);
+            assert_eq!(port.flag().address() as u32, base + 0x018);
+            assert_eq!(
+                port.integer_baud_rate_divisor().address() as u32,
+                base + 0x024
+            );
+            assert_eq!(
+                port.fractional_baud_rate_divisor().address() as u32,
+                base + 0x028
+            );
+            assert_eq!(port.line_control().address() as u32, base + 0x02C);
+            assert_eq!(port.control().address() as u32, base + 0x030);
+            assert_eq!(
+                port.interrupt_fifo_level_select().address() as u32,
+                base + 0x034
+            );
+            assert_eq!(port.interrupt_mask().address() as u32, base + 0x038);
+            assert_eq!(
+                port.masked_interrupt_status().address() as u32,
+                base + 0x040
+            );
+            assert_eq!(port.interrupt_clear().address() as u32, base + 0x044);
+        }
+        assert_eq!(
+            ports.map(|(port, _)| port.interrupt()),
+            [5, 6, 33, 59, 60, 61, 62, 63]
+        );
+
+        let rcgcuart = Board.uart_run_mode_clock_gating_control();
+        assert_eq!(rcgcuart.address() as u32, 0x400F_E618);
+        assert_eq!(Board.uart_peripheral_ready().address() as u32, 0x400F_EA18);
+    }
+
+    #[test]
+    fn setup_disables_configures_then_enables() {
+        peripherals_ready();
+
+        let board = Board;
+        let port = Port::Zero;
+
+        setup_port(
+            board,
+            port,
+            PortOptions {
+                baud_rate: 115_200,
+                fifos: true,
+                word_length: WordLength::Eight,
+                parity: Parity::None,
+                stop_bits: StopBits::One,
+            },
+            16_000_000,
+        )
+        .unwrap();
+
+        simulated::with(|memory| {
+            assert_eq!(
+                memory.written_registers(),
+                [
+                    bit_band::alias(board.uart_run_mode_clock_gating_control().address(), 0)
+                        .unwrap(),
+                    port.control().address(),
+                    port.integer_baud_rate_divisor().address(),
+                    port.fractional_baud_rate_divisor().address(),
+                    port.line_control().address(),
+                    port.control().address(),
+                ]
+            );
+            assert_eq!(memory.value(port.integer_baud_rate_divisor().address()), 8);
+            assert_eq!(
+                memory.value(port.fractional_baud_rate_divisor().address()),
+                44
+            );
+            assert_eq!(memory.value(port.line_control().address()), 0b0111_0000);
+            assert_eq!(memory.value(port.control().address()), 0b1);
+        });
+    }
+
+    #[test]
+    fn line_settings_set_parity_and_stop_bits() {
+        peripherals_ready();
+
+        let frames = [
+            (WordLength::Seven, Parity::Even, StopBits::One, 0b0100_0110),
+            (WordLength::Eight, Parity::None, StopBits::Two, 0b0110_1000),
+            (WordLength::Eight, Parity::Mark, StopBits::One, 0b1110_0010),
+            (WordLength::Eight, Parity::Space, StopBits::One, 0b1110_0110),
+        ];
+
+        for (word_length, parity, stop_bits, line_control) in frames {
+            let port = Port::One;
+
+            setup_port(
+                Board,
+                port,
+                PortOptions {
+                    baud_rate: 9600,
+                    fifos: false,
+                    word_length,
+                    parity,
+                    stop_bits,
+                },
+                16_000_000,
+            )
+            .unwrap();
+
+            simulated::with(|memory| {
+                assert_eq!(memory.value(port.line_control().address()), line_control);
+            });
+        }
+    }
+
+    #[test]
+    fn baud_rate_divisors_are_exact_integers() {
+        let divisor = |system_clock_hz, baud_rate| {
+            baud_rate_divisor(system_clock_hz, baud_rate).map(|divisor| {
+                (
+                    divisor.integer,
+                    divisor.fraction,
+                    divisor.high_speed,
+                    divisor.baud_rate.achieved,
+                )
+            })
+        };
+
+        // Page 896 of data sheet's example: 20 MHz at 115,200 baud is a divisor of 10.8507
+        assert_eq!(divisor(20_000_000, 115_200), Ok((10, 54, false, 115_274)));
+        assert_eq!(divisor(80_000_000, 9_600), Ok((520, 53, false, 9_600)));
+
+        // Too fast for ClkDiv = 16, but not for 8
+        assert_eq!(divisor(16_000_000, 2_000_000), Ok((1, 0, true, 2_000_000)));
+
+        // Too fast even for ClkDiv = 8
+        assert_eq!(
+            divisor(16_000_000, 3_000_000),
+            Err(SetupError::UnreachableBaudRate(BaudRate {
+                requested: 3_000_000,
+                achieved: 2_000_000
+            }))
+        );
+        // Too slow for the 16-bit integer part
+        assert_eq!(
+            divisor(80_000_000, 50),
+            Err(SetupError::UnreachableBaudRate(BaudRate {
+                requested: 50,
+                achieved: 76
+            }))
+        );
+        assert_eq!(divisor(80_000_000, 0), Err(SetupError::ZeroBaudRate));
+
+        let baud_rate = baud_rate_divisor(20_000_000, 115_200).unwrap().baud_rate;
+        assert!((baud_rate.error_percent() - 0.0637).abs() < 0.001);
+    }
+
+    #[test]
+    fn unreachable_baud_rates_leave_the_port_alone() {
+        let result = setup_port(
+            Board,
+            Port::Two,
+            PortOptions {
+                baud_rate: 10_000_000,
+                fifos: true,
+                word_length: WordLength::Eight,
+                parity: Parity::None,
+                stop_bits: StopBits::One,
+            },
+            16_000_000,
+        );
+
+        assert!(matches!(result, Err(SetupError::UnreachableBaudRate(_))));
+        simulated::with(|memory| assert!(memory.writes().is_empty()));
+    }
+
+    #[test]
+    fn bytes_that_came_in_wrong_are_errors_and_counted() {
+        peripherals_ready();
+
+        let port = Port::Two;
+        let [receive_pin] = steal_readable_pins(
+            GPIOPort::D,
+            [Pin::Six],
+            ReadablePinOptions {
+                function: Function::U2Rx,
+                pull: Pull::Neither,
+            },
+        )
+        .unwrap()
+        .into_pins();
+        let mut usable_port = setup_port(
+            Board,
+            port,
+            PortOptions {
+                baud_rate: 9600,
+                fifos: true,
+                word_length: WordLength::Eight,
+                parity: Parity::Even,
+                stop_bits: StopBits::One,
+            },
+            16_000_000,
+        )
+        .unwrap();
+        usable_port.enable_interrupts(InterruptOptions::default());
+
+        // A FIFO's worth of good bytes, then a FIFO's worth with bad parity
+        // (the FIFO never empties here, so the handler stops once it's read a FIFO's worth)
+        for data in [b'a' as u32, (1 << 9) | b'b' as u32] {
+            simulated::with(|memory| {
+                memory.preset(port.masked_interrupt_status().address(), 1 << 6);
+                memory.preset(port.flag().address(), 0);
+                memory.preset(port.data().address(), data);
+            });
+            interrupts::handle(port);
+        }
+
+        let mut bytes = [0; 20];
+        assert_eq!(usable_port.read(&receive_pin, &mut bytes), Ok(16));
+        assert_eq!(bytes[..16], [b'a'; 16]);
+        assert_eq!(
+            usable_port.read(&receive_pin, &mut bytes),
+            Err(ReceiveError::Parity)
+        );
+        assert_eq!(
+            usable_port.read_byte(&receive_pin, false),
+            Some(Err(ReceiveError::Parity))
+        );
+        assert_eq!(
+            usable_port.error_counts(),
+            ErrorCounts {
+                parity: 16,
+                ..Default::default()
+            }
+        );
+
+        usable_port.clear_errors();
+        assert_eq!(usable_port.error_counts(), ErrorCounts::default());
+        simulated::with(|memory| {
+            assert!(memory.writes().contains(&(port.error_clear().address(), 0)));
+        });
+    }
+
+    #[test]
+    fn breaks_are_told_apart_from_framing_errors() {
+        assert_eq!(ReceiveError::check(b'z' as u32), Ok(b'z'));
+        assert_eq!(
+            ReceiveError::check((1 << 10) | (1 << 8)),
+            Err(ReceiveError::Break)
+        );
+        assert_eq!(
+            ReceiveError::check((1 << 8) | b'z' as u32),
+            Err(ReceiveError::Framing)
+        );
+        assert_eq!(
+            ReceiveError::check((1 << 11) | b'z' as u32),
+            Err(ReceiveError::Overrun(b'z'))
+        );
+    }
+
+    #[test]
+    fn every_port_can_be_set_up_on_its_own_pins() {
+        peripherals_ready();
+
+        let ports = [
+            Port::Zero,
+            Port::One,
+            Port::Two,
+            Port::Three,
+            Port::Four,
+            Port::Five,
+            Port::Six,
+            Port::Seven,
+        ];
+
+        for (n, port) in ports.into_iter().enumerate() {
+            for assignment in port.pin_assignments() {
+                assert!(mux(
+                    assignment.gpio_port,
+                    assignment.receive_pin,
+                    assignment.receive_function
+                )
+                .is_some());
+                assert!(mux(
+                    assignment.gpio_port,
+                    assignment.transmit_pin,
+                    assignment.transmit_function
+                )
+                .is_some());
+            }
+
+            setup_port(
+                Board,
+                port,
+                PortOptions {
+                    baud_rate: 115_200,
+                    fifos: true,
+                    word_length: WordLength::Eight,
+                    parity: Parity::None,
+                    stop_bits: StopBits::One,
+                },
+                80_000_000,
+            )
+            .unwrap();
+
+            simulated::with(|memory| {
+                assert_eq!(
+                    memory.value(Board.uart_run_mode_clock_gating_control().address()),
+                    (1 << (n + 1)) - 1
+                );
+                assert_eq!(memory.value(port.control().address()), 0b1);
+            });
+        }
+    }
+}